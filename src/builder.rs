@@ -0,0 +1,440 @@
+use crate::linktarget::{self, ItemMetadata};
+use crate::{
+    ExtraData, FileAttributeFlags, HotkeyFlags, HotkeyKey, HotkeyModifiers, MSLinkError,
+    NetworkProviderType, ShellLink, ShowCommand, WindowsVersion,
+};
+#[cfg(test)]
+use crate::{LinkFlags, ShellItem};
+use std::path::{Path, PathBuf};
+
+/// A builder for [`ShellLink`]s.
+///
+/// Unlike [`ShellLink::new`], building doesn't touch the file system, so links to Windows
+/// paths can be created on any host. All settings are checked by [`build`](Self::build).
+///
+/// ```
+/// use mslnk::{HotkeyFlags, HotkeyKey, HotkeyModifiers, ShellLinkBuilder, ShowCommand};
+///
+/// let sl = ShellLinkBuilder::new(r"C:\Program Files\App\app.exe")
+///     .arguments("--profile default")
+///     .description("Start App")
+///     .icon(r"C:\Program Files\App\app.ico", 0)
+///     .hotkey(HotkeyFlags::new(
+///         HotkeyKey::KeyA,
+///         HotkeyModifiers::HOTKEYF_CONTROL | HotkeyModifiers::HOTKEYF_ALT,
+///     ))
+///     .show_command(ShowCommand::ShowMaximized)
+///     .build()
+///     .unwrap();
+/// assert_eq!(sl.working_dir().as_deref(), Some(r"C:\Program Files\App"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShellLinkBuilder {
+    target: PathBuf,
+    target_is_dir: Option<bool>,
+    file_size: u64,
+    file_attributes: Option<FileAttributeFlags>,
+    drive_mapping: Option<(String, NetworkProviderType)>,
+    short_path: Option<PathBuf>,
+    windows_version: WindowsVersion,
+    arguments: Option<String>,
+    working_dir: Option<String>,
+    description: Option<String>,
+    icon_location: Option<String>,
+    icon_index: i32,
+    hotkey: HotkeyFlags,
+    show_command: ShowCommand,
+    creation_time: u64,
+    access_time: u64,
+    write_time: u64,
+    extra_data: Vec<ExtraData>,
+}
+
+impl ShellLinkBuilder {
+    /// Start building a link to `target`, an absolute drive or UNC path.
+    pub fn new<P: AsRef<Path>>(target: P) -> Self {
+        Self {
+            target: target.as_ref().to_owned(),
+            target_is_dir: None,
+            file_size: 0,
+            file_attributes: None,
+            drive_mapping: None,
+            short_path: None,
+            windows_version: WindowsVersion::default(),
+            arguments: None,
+            working_dir: None,
+            description: None,
+            icon_location: None,
+            icon_index: 0,
+            hotkey: HotkeyFlags::new(HotkeyKey::NoKeyAssigned, HotkeyModifiers::NO_MODIFIER),
+            show_command: ShowCommand::ShowNormal,
+            creation_time: 0,
+            access_time: 0,
+            write_time: 0,
+            extra_data: vec![],
+        }
+    }
+
+    /// Whether the target is a directory. By default, a target ending with a separator is a
+    /// directory and any other target is a file.
+    pub fn target_is_dir(mut self, is_dir: bool) -> Self {
+        self.target_is_dir = Some(is_dir);
+        self
+    }
+
+    /// The size of the target file in bytes, stored in the header and the target's item.
+    pub fn file_size(mut self, file_size: u64) -> Self {
+        self.file_size = file_size;
+        self
+    }
+
+    /// The file attributes of the target, replacing the ones derived from
+    /// [`target_is_dir`](Self::target_is_dir).
+    pub fn file_attributes(mut self, file_attributes: FileAttributeFlags) -> Self {
+        self.file_attributes = Some(file_attributes);
+        self
+    }
+
+    /// Record that the target's drive is mapped to the share `net_name`, see
+    /// [`ShellLink::new_mapped`].
+    pub fn drive_mapping<S: Into<String>>(
+        mut self,
+        net_name: S,
+        network_provider_type: NetworkProviderType,
+    ) -> Self {
+        self.drive_mapping = Some((net_name.into(), network_provider_type));
+        self
+    }
+
+    /// The target's path in 8.3 short names, such as `C:\PROGRA~1\App\app.exe` as Windows
+    /// reports it, replacing the short names generated for the items. It must be on the
+    /// target's drive or share and have as many components.
+    pub fn short_path<P: AsRef<Path>>(mut self, short_path: P) -> Self {
+        self.short_path = Some(short_path.as_ref().to_owned());
+        self
+    }
+
+    /// The oldest Windows version the link is written for, selecting the layout of the
+    /// extension blocks in its items. Defaults to [`WindowsVersion::Windows7`].
+    pub fn windows_version(mut self, windows_version: WindowsVersion) -> Self {
+        self.windows_version = windows_version;
+        self
+    }
+
+    /// The command line arguments passed to the target.
+    pub fn arguments<S: Into<String>>(mut self, arguments: S) -> Self {
+        self.arguments = Some(arguments.into());
+        self
+    }
+
+    /// The working directory, replacing the default of the target's parent directory.
+    pub fn working_dir<S: Into<String>>(mut self, working_dir: S) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// The description shown as the link's comment, stored as its name string.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The file holding the icon, and the index of the icon within it.
+    pub fn icon<S: Into<String>>(mut self, icon_location: S, icon_index: i32) -> Self {
+        self.icon_location = Some(icon_location.into());
+        self.icon_index = icon_index;
+        self
+    }
+
+    /// The keystroke that launches the link.
+    pub fn hotkey(mut self, hotkey: HotkeyFlags) -> Self {
+        self.hotkey = hotkey;
+        self
+    }
+
+    /// The window state of the launched application.
+    pub fn show_command(mut self, show_command: ShowCommand) -> Self {
+        self.show_command = show_command;
+        self
+    }
+
+    /// The creation, access and write times of the target, as FILETIMEs. They are stored in
+    /// the header, and in the target's item with a resolution of two seconds.
+    pub fn timestamps(mut self, creation_time: u64, access_time: u64, write_time: u64) -> Self {
+        self.creation_time = creation_time;
+        self.access_time = access_time;
+        self.write_time = write_time;
+        self
+    }
+
+    /// Add an extra data block. The link flag announcing the block, if any, is set.
+    pub fn extra_data(mut self, block: ExtraData) -> Self {
+        self.extra_data.push(block);
+        self
+    }
+
+    /// Check the settings and build the link, see [`ShellLink::validate`].
+    pub fn build(self) -> Result<ShellLink, MSLinkError> {
+        let target_path = linktarget::TargetPath::from_path(&self.target)?;
+        let is_dir = self
+            .target_is_dir
+            .unwrap_or_else(|| self.target.to_string_lossy().ends_with(&['\\', '/'][..]));
+        if !is_dir && target_path.file_name().is_none() {
+            return Err(MSLinkError::unsupported_path(
+                &target_path.path(),
+                "is a drive or share root and can't be a file",
+            ));
+        }
+        if self.hotkey.key() == &HotkeyKey::NoKeyAssigned
+            && self.hotkey.modifiers() != &HotkeyModifiers::NO_MODIFIER
+        {
+            return Err(MSLinkError::InvalidFlags(
+                "the hotkey has modifiers but no key".to_owned(),
+            ));
+        }
+
+        let target = ItemMetadata {
+            file_size: self.file_size,
+            creation_time: self.creation_time,
+            access_time: self.access_time,
+            write_time: self.write_time,
+            short_name: None,
+        };
+        let mut metadata = ItemMetadata::for_target(target_path.components.len(), target.clone());
+        if let Some(short_path) = &self.short_path {
+            let short_path = linktarget::TargetPath::from_path(short_path)?;
+            if !short_path.root().eq_ignore_ascii_case(&target_path.root())
+                || short_path.components.len() != target_path.components.len()
+            {
+                return Err(MSLinkError::unsupported_path(
+                    &short_path.path(),
+                    "is not a short path of the target",
+                ));
+            }
+            for (item, short_name) in metadata.iter_mut().zip(short_path.components) {
+                item.short_name = Some(short_name);
+            }
+        }
+        let mut sl = ShellLink::with_target(
+            &target_path,
+            is_dir,
+            &target,
+            &metadata,
+            self.windows_version,
+        )?;
+        if let Some((net_name, network_provider_type)) = &self.drive_mapping {
+            sl.set_drive_mapping(&target_path, net_name, *network_provider_type)?;
+        }
+        if let Some(working_dir) = self.working_dir {
+            sl.set_working_dir(Some(working_dir));
+        }
+        sl.set_name(self.description);
+        sl.set_arguments(self.arguments);
+        sl.set_icon_location(self.icon_location);
+
+        let header = sl.header_mut();
+        if let Some(file_attributes) = self.file_attributes {
+            header.set_file_attributes(file_attributes);
+        }
+        header.set_icon_index(self.icon_index);
+        *header.hotkey_mut() = self.hotkey;
+        header.set_show_command(self.show_command);
+        for block in self.extra_data {
+            if let Some(flag) = block.link_flag() {
+                sl.header_mut().update_link_flags(flag, true);
+            }
+            sl.extra_data_mut().push(block);
+        }
+
+        sl.validate()?;
+        Ok(sl)
+    }
+}
+
+#[test]
+fn test_build_link() {
+    let sl = ShellLinkBuilder::new(r"D:\Tools\app.exe")
+        .file_size(1024)
+        .arguments("--profile default")
+        .description("App")
+        .icon(r"D:\Tools\app.ico", 2)
+        .show_command(ShowCommand::ShowMinNoActive)
+        // 2008-09-12 20:27:19.5 UTC, accessed and written a second later
+        .timestamps(
+            128_657_248_395_000_000,
+            128_657_248_405_000_000,
+            128_657_248_405_000_000,
+        )
+        .extra_data(ExtraData::environment_variables(r"%TOOLS%\app.exe").unwrap())
+        .build()
+        .unwrap();
+
+    let flags = *sl.header().link_flags();
+    assert!(flags.contains(
+        LinkFlags::HAS_LINK_TARGET_ID_LIST
+            | LinkFlags::HAS_NAME
+            | LinkFlags::HAS_ARGUMENTS
+            | LinkFlags::HAS_ICON_LOCATION
+            | LinkFlags::HAS_WORKING_DIR
+            | LinkFlags::HAS_EXP_STRING
+    ));
+    assert_eq!(sl.working_dir().as_deref(), Some(r"D:\Tools"));
+    assert_eq!(sl.header().icon_index(), 2);
+    assert_eq!(sl.header().file_size(), 1024);
+    assert_eq!(sl.header().write_time(), 128_657_248_405_000_000);
+    match sl.linktarget().unwrap().items().last().unwrap() {
+        ShellItem::FileEntry(item) => {
+            assert_eq!(item.file_size(), 1024);
+            assert_eq!(item.modification_time(), 0xa36a_392c);
+            let extension = item.extension().unwrap();
+            assert_eq!(extension.creation_time(), 0xa369_392c);
+            assert_eq!(extension.access_time(), 0xa36a_392c);
+        }
+        item => panic!("not a file entry: {:?}", item),
+    }
+    assert_eq!(sl.header().show_command(), &ShowCommand::ShowMinNoActive);
+
+    let parsed = ShellLink::from_bytes(&sl.to_bytes().unwrap()).unwrap();
+    assert_eq!(parsed.name().as_deref(), Some("App"));
+    assert_eq!(parsed.extra_data().len(), 1);
+}
+
+#[test]
+fn test_build_short_names() {
+    let names = |sl: &ShellLink| -> Vec<(String, String)> {
+        sl.linktarget()
+            .unwrap()
+            .items()
+            .filter_map(|item| match item {
+                ShellItem::FileEntry(item) => {
+                    Some((item.name().to_owned(), item.long_name().to_owned()))
+                }
+                _ => None,
+            })
+            .collect()
+    };
+    let target = r"C:\Program Files (x86)\My App\app.exe";
+    let sl = ShellLinkBuilder::new(target).build().unwrap();
+    assert_eq!(
+        names(&sl),
+        [
+            ("PROGRA~1", "Program Files (x86)"),
+            ("MYAPP~1", "My App"),
+            ("app.exe", "app.exe")
+        ]
+        .map(|(short, long)| (short.to_owned(), long.to_owned()))
+    );
+
+    let sl = ShellLinkBuilder::new(target)
+        .short_path(r"c:\PROGRA~2\MYAPP~1\APP.EXE")
+        .build()
+        .unwrap();
+    assert_eq!(names(&sl)[0].0, "PROGRA~2");
+    assert_eq!(names(&sl)[2].0, "APP.EXE");
+    assert_eq!(
+        sl.linktarget().unwrap().to_string(),
+        r"This PC > C:\ > Program Files (x86) > My App > app.exe"
+    );
+    let parsed = ShellLink::from_bytes(&sl.to_bytes().unwrap()).unwrap();
+    assert_eq!(names(&parsed), names(&sl));
+
+    assert!(ShellLinkBuilder::new(target)
+        .short_path(r"D:\PROGRA~2\MYAPP~1\APP.EXE")
+        .build()
+        .is_err());
+    assert!(ShellLinkBuilder::new(target)
+        .short_path(r"C:\PROGRA~2\APP.EXE")
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_build_windows_versions() {
+    let versions = [
+        (WindowsVersion::WindowsXp, 3, 0x14, 20),
+        (WindowsVersion::WindowsVista, 7, 0x26, 38),
+        (WindowsVersion::Windows7, 8, 0x2a, 42),
+        (WindowsVersion::Windows8, 9, 0x2e, 46),
+    ];
+    for (windows_version, version, version_id, name_offset) in versions {
+        let mut sl = ShellLinkBuilder::new(r"C:\Program Files\app.exe")
+            .windows_version(windows_version)
+            .build()
+            .unwrap();
+        let parsed = ShellLink::from_bytes(&sl.to_bytes().unwrap()).unwrap();
+        let list = parsed.linktarget().unwrap();
+        for (item, id) in list.items().zip(list.item_ids()).skip(2) {
+            let item = match item {
+                ShellItem::FileEntry(item) => item,
+                item => panic!("not a file entry: {:?}", item),
+            };
+            let block = item.extension().unwrap();
+            assert_eq!(block.version(), version);
+            assert_eq!(block.version_id(), version_id);
+            // the offset of the block counts the ItemIDSize field
+            let start = block.first_offset() as usize - 2;
+            let name = &id.data()[start + name_offset..];
+            assert!(name.starts_with(&crate::stringdata::to_unicode_z(item.long_name())));
+        }
+        assert_eq!(list.to_string(), r"This PC > C:\ > Program Files > app.exe");
+
+        sl.retarget(r"C:\Tools\tool.exe").unwrap();
+        assert_eq!(
+            sl.linktarget().unwrap().windows_version(),
+            Some(windows_version)
+        );
+    }
+}
+
+#[test]
+fn test_build_directory_link() {
+    let sl = ShellLinkBuilder::new(r"D:\").build().unwrap();
+    assert!(sl
+        .header()
+        .file_attributes()
+        .contains(FileAttributeFlags::FILE_ATTRIBUTE_DIRECTORY));
+    assert_eq!(sl.working_dir(), &None);
+
+    let sl = ShellLinkBuilder::new(r"D:\Tools")
+        .target_is_dir(true)
+        .working_dir(r"C:\")
+        .build()
+        .unwrap();
+    assert_eq!(sl.working_dir().as_deref(), Some(r"C:\"));
+}
+
+#[test]
+fn test_build_errors() {
+    assert!(ShellLinkBuilder::new("app.exe").build().is_err());
+    assert!(ShellLinkBuilder::new(r"D:\")
+        .target_is_dir(false)
+        .build()
+        .is_err());
+    assert!(ShellLinkBuilder::new(r"D:\app.exe")
+        .hotkey(HotkeyFlags::new(
+            HotkeyKey::NoKeyAssigned,
+            HotkeyModifiers::HOTKEYF_ALT
+        ))
+        .build()
+        .is_err());
+    assert!(ShellLinkBuilder::new(r"D:\app.exe")
+        .file_attributes(
+            FileAttributeFlags::FILE_ATTRIBUTE_NORMAL | FileAttributeFlags::FILE_ATTRIBUTE_HIDDEN
+        )
+        .build()
+        .is_err());
+    assert!(ShellLinkBuilder::new(r"D:\app.exe")
+        .arguments("a".repeat(70000))
+        .build()
+        .is_err());
+    let env = ExtraData::environment_variables(r"%TOOLS%\app.exe").unwrap();
+    assert!(ShellLinkBuilder::new(r"D:\app.exe")
+        .extra_data(env.clone())
+        .extra_data(env)
+        .build()
+        .is_err());
+    assert!(ShellLinkBuilder::new(r"\\server\share\app.exe")
+        .drive_mapping(r"\\server\share", NetworkProviderType::Lanman)
+        .build()
+        .is_err());
+}
@@ -0,0 +1,251 @@
+use crate::Guid;
+use std::fmt;
+
+/// A category of the Control Panel in category view, such as Programs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ControlPanelCategory {
+    /// All Control Panel Items, the list of every applet.
+    AllItems,
+    /// Appearance and Personalization.
+    AppearanceAndPersonalization,
+    /// Hardware and Sound.
+    HardwareAndSound,
+    /// Network and Internet.
+    NetworkAndInternet,
+    /// Sounds, Speech, and Audio Devices, on Windows XP.
+    SoundsSpeechAndAudioDevices,
+    /// System and Security.
+    SystemAndSecurity,
+    /// Clock, Language, and Region.
+    ClockLanguageAndRegion,
+    /// Ease of Access.
+    EaseOfAccess,
+    /// Programs.
+    Programs,
+    /// User Accounts.
+    UserAccounts,
+    /// Security Center, on Windows XP.
+    SecurityCenter,
+    /// Mobile PC.
+    MobilePc,
+}
+
+impl ControlPanelCategory {
+    const ALL: [Self; 12] = [
+        Self::AllItems,
+        Self::AppearanceAndPersonalization,
+        Self::HardwareAndSound,
+        Self::NetworkAndInternet,
+        Self::SoundsSpeechAndAudioDevices,
+        Self::SystemAndSecurity,
+        Self::ClockLanguageAndRegion,
+        Self::EaseOfAccess,
+        Self::Programs,
+        Self::UserAccounts,
+        Self::SecurityCenter,
+        Self::MobilePc,
+    ];
+
+    /// The identifier stored in the category's item.
+    pub fn id(self) -> u32 {
+        Self::ALL.iter().position(|&c| c == self).unwrap() as u32
+    }
+
+    /// The category with the identifier `id`, if it is a known one.
+    pub fn from_id(id: u32) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// The English display name of the category.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AllItems => "All Control Panel Items",
+            Self::AppearanceAndPersonalization => "Appearance and Personalization",
+            Self::HardwareAndSound => "Hardware and Sound",
+            Self::NetworkAndInternet => "Network and Internet",
+            Self::SoundsSpeechAndAudioDevices => "Sounds, Speech, and Audio Devices",
+            Self::SystemAndSecurity => "System and Security",
+            Self::ClockLanguageAndRegion => "Clock, Language, and Region",
+            Self::EaseOfAccess => "Ease of Access",
+            Self::Programs => "Programs",
+            Self::UserAccounts => "User Accounts",
+            Self::SecurityCenter => "Security Center",
+            Self::MobilePc => "Mobile PC",
+        }
+    }
+}
+
+impl fmt::Display for ControlPanelCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The canonical names of Control Panel applets and their CLSIDs, as listed in "Canonical
+/// Names of Control Panel Items".
+const CANONICAL_NAMES: &[(&str, &str)] = &[
+    (
+        "Microsoft.ActionCenter",
+        "{BB64F8A7-BEE7-4E1A-AB8D-7D8273F7FDB6}",
+    ),
+    (
+        "Microsoft.AdministrativeTools",
+        "{D20EA4E1-3957-11D2-A40B-0C5020524153}",
+    ),
+    (
+        "Microsoft.AutoPlay",
+        "{9C60DE1E-E5FC-40F4-A487-460851A8D915}",
+    ),
+    (
+        "Microsoft.BackupAndRestore",
+        "{B98A2BEA-7D42-4558-8BD1-832F41BAC6FD}",
+    ),
+    (
+        "Microsoft.BitLockerDriveEncryption",
+        "{D9EF8727-CAC2-4E60-809E-86F80A666C91}",
+    ),
+    (
+        "Microsoft.ColorManagement",
+        "{B2C761C6-29BC-4F19-9251-E6195265BAF1}",
+    ),
+    (
+        "Microsoft.CredentialManager",
+        "{1206F5F1-0569-412C-8FEC-3204630DFB70}",
+    ),
+    (
+        "Microsoft.DateAndTime",
+        "{E2E7934B-DCE5-43C4-9576-7FE4F75E7480}",
+    ),
+    (
+        "Microsoft.DefaultPrograms",
+        "{17CD9488-1228-4B2F-88CE-4298E93E0966}",
+    ),
+    (
+        "Microsoft.DeviceManager",
+        "{74246BFC-4C96-11D0-ABEF-0020AF6B0B7A}",
+    ),
+    (
+        "Microsoft.DevicesAndPrinters",
+        "{A8A91A66-3A7D-4424-8D24-04E180695C7A}",
+    ),
+    (
+        "Microsoft.EaseOfAccessCenter",
+        "{D555645E-D4F8-4C29-A827-D93C859C4F2A}",
+    ),
+    (
+        "Microsoft.FileHistory",
+        "{F6B6E965-E9B2-444B-9286-10C9152EDBC5}",
+    ),
+    ("Microsoft.Fonts", "{93412589-74D4-4E4E-AD0E-E0CB621440FD}"),
+    (
+        "Microsoft.IndexingOptions",
+        "{87D66A43-7B11-4A28-9811-C86EE395ACF7}",
+    ),
+    (
+        "Microsoft.InternetOptions",
+        "{A3DD4F92-658A-410F-84FD-6FBBBEF2FFFE}",
+    ),
+    (
+        "Microsoft.Keyboard",
+        "{725BE8F7-668E-4C7B-8F90-46BDB0936430}",
+    ),
+    ("Microsoft.Mouse", "{6C8EEC18-8D75-41B2-A177-8831D59D2D50}"),
+    (
+        "Microsoft.NetworkAndSharingCenter",
+        "{8E908FC9-BECC-40F6-915B-F4CA0E70D03D}",
+    ),
+    (
+        "Microsoft.Personalization",
+        "{ED834ED6-4B5A-4BFE-8F11-A626DCB6A921}",
+    ),
+    (
+        "Microsoft.PowerOptions",
+        "{025A5937-A6BE-4686-A844-36FE4BEC8B6D}",
+    ),
+    (
+        "Microsoft.ProgramsAndFeatures",
+        "{7B81BE6A-CE2B-4676-A29E-EB907A5126C5}",
+    ),
+    (
+        "Microsoft.Recovery",
+        "{9FE63AFD-59CF-4419-9775-ABCC3849F861}",
+    ),
+    (
+        "Microsoft.RegionAndLanguage",
+        "{62D8ED13-C9D0-4CE8-A914-47DD628FB1B0}",
+    ),
+    ("Microsoft.Sound", "{F2DDFC82-8F12-4CDD-B7DC-D4FE1425AA4D}"),
+    (
+        "Microsoft.SpeechRecognition",
+        "{58E3C745-D971-4081-9034-86E34B30836A}",
+    ),
+    (
+        "Microsoft.StorageSpaces",
+        "{F942C606-0914-47AB-BE56-1321B8035096}",
+    ),
+    (
+        "Microsoft.SyncCenter",
+        "{9C73F5E5-7AE7-4E32-A8E8-8D23B85255BF}",
+    ),
+    ("Microsoft.System", "{BB06C0E4-D293-4F75-8A90-CB05B6477EEE}"),
+    (
+        "Microsoft.Troubleshooting",
+        "{C58C4893-3BE0-4B45-ABB5-A63E4B8C8651}",
+    ),
+    (
+        "Microsoft.UserAccounts",
+        "{60632754-C523-4B62-B45C-4172DA012619}",
+    ),
+    (
+        "Microsoft.WindowsDefender",
+        "{D8559EB9-20C0-410E-BEDA-7ED416AECC2A}",
+    ),
+    (
+        "Microsoft.WindowsFirewall",
+        "{4026492F-2F69-46B8-B9BF-5654FC07E423}",
+    ),
+    (
+        "Microsoft.WindowsUpdate",
+        "{36EEF7DB-88AD-4E81-AD49-0E313F0C35F8}",
+    ),
+];
+
+/// The CLSID of the Control Panel applet with the canonical name `name`, such as
+/// `Microsoft.WindowsUpdate`. Names are matched case-insensitively.
+pub(crate) fn clsid_of(name: &str) -> Option<Guid> {
+    CANONICAL_NAMES
+        .iter()
+        .find(|(canonical, _)| canonical.eq_ignore_ascii_case(name))
+        .and_then(|(_, clsid)| clsid.parse().ok())
+}
+
+/// The canonical name of the Control Panel applet `clsid`, if it is a known one.
+pub(crate) fn canonical_name_of(clsid: Guid) -> Option<&'static str> {
+    let clsid = clsid.to_string();
+    CANONICAL_NAMES
+        .iter()
+        .find(|(_, known)| known.eq_ignore_ascii_case(&clsid))
+        .map(|&(name, _)| name)
+}
+
+#[test]
+fn test_canonical_names() {
+    let clsid = clsid_of("microsoft.windowsupdate").unwrap();
+    assert_eq!(clsid.to_string(), "{36EEF7DB-88AD-4E81-AD49-0E313F0C35F8}");
+    assert_eq!(canonical_name_of(clsid), Some("Microsoft.WindowsUpdate"));
+    assert!(clsid_of("Microsoft.Unknown").is_none());
+    // every name and CLSID is listed once
+    for (name, clsid) in CANONICAL_NAMES {
+        let parsed = clsid_of(name).unwrap();
+        assert_eq!(&parsed.to_string(), clsid);
+        assert_eq!(canonical_name_of(parsed), Some(*name));
+    }
+
+    assert_eq!(ControlPanelCategory::Programs.id(), 8);
+    assert_eq!(
+        ControlPanelCategory::from_id(5),
+        Some(ControlPanelCategory::SystemAndSecurity)
+    );
+    assert!(ControlPanelCategory::from_id(12).is_none());
+}
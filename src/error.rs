@@ -2,35 +2,288 @@ use std::error::Error;
 use std::fmt;
 use std::io;
 use std::num;
+
+/// The error type of this crate.
+///
+/// The variants let callers tell apart failures worth retrying, such as [`Io`](Self::Io), from
+/// links that are corrupt or can't be represented.
 #[derive(Debug)]
-pub struct MSLinkError {
-    kind: String,
-    message: String,
+#[non_exhaustive]
+pub enum MSLinkError {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The data doesn't start with a ShellLinkHeader, the header size or CLSID is wrong.
+    InvalidHeader,
+    /// A structure ended before all of its fields could be read.
+    Truncated {
+        /// The structure that is cut short.
+        structure: String,
+        /// The offset of the structure from the start of the link data.
+        offset: usize,
+    },
+    /// A StringData field has more characters than its 16-bit count can express.
+    StringTooLong {
+        /// The StringData field, such as `COMMAND_LINE_ARGUMENTS`.
+        field: String,
+        /// The length of the string in characters.
+        len: usize,
+    },
+    /// A structure would exceed the size its length field can express.
+    TooLarge {
+        /// The structure that is too large.
+        structure: String,
+        /// The size the structure would have.
+        size: usize,
+        /// The largest size the structure can have.
+        limit: usize,
+    },
+    /// A size field doesn't match the structure it belongs to.
+    SizeMismatch {
+        /// The structure with the wrong size.
+        structure: String,
+        /// The size the structure has.
+        size: usize,
+        /// The size the structure should have.
+        expected: usize,
+    },
+    /// The target can't be stored in a link.
+    UnsupportedPath {
+        /// The target path.
+        path: String,
+        /// Why the target is not supported.
+        reason: String,
+    },
+    /// LinkFlags, file attributes or hotkey flags contradict each other or the link's
+    /// contents.
+    InvalidFlags(String),
+    /// A field holds a value the specification doesn't define.
+    InvalidValue {
+        /// The field holding the value.
+        field: String,
+        /// The value.
+        value: u32,
+    },
+    /// A string is not a GUID in registry format.
+    InvalidGuid(String),
+    /// Data follows the end of the link.
+    TrailingData {
+        /// The number of bytes after the TerminalBlock.
+        len: usize,
+    },
+    /// An extra data block has an unknown signature.
+    UnknownBlock {
+        /// The BlockSignature of the block.
+        signature: u32,
+    },
+    /// Parsing a link failed, with the location of the malformed data.
+    Parse {
+        /// The structures enclosing the malformed data, outermost first, such as
+        /// `LinkTargetIDList/ItemID[3]`.
+        path: String,
+        /// The offset of the malformed data from the start of the link data.
+        offset: usize,
+        /// What is wrong with the data.
+        source: Box<MSLinkError>,
+    },
 }
-/// fix :doesn't satisfy `MSLinkError: std::error::Error`
-impl Error for MSLinkError {}
+
+impl MSLinkError {
+    /// A structure would exceed the size its length field can express.
+    pub(crate) fn too_large(structure: &str, size: usize, limit: usize) -> Self {
+        Self::TooLarge {
+            structure: structure.to_owned(),
+            size,
+            limit,
+        }
+    }
+
+    /// A structure starting at the beginning of the data handed to a reader ended before all
+    /// of its fields could be read. See [`within`](Self::within).
+    pub(crate) fn truncated(structure: &str) -> Self {
+        Self::Truncated {
+            structure: structure.to_owned(),
+            offset: 0,
+        }
+    }
+
+    /// Record that the error occurred in `structure`, found at `base` within the data handed
+    /// to the enclosing reader. Offsets are relative to the data handed to the innermost reader
+    /// until the outermost one, reading the whole link, makes them absolute.
+    pub(crate) fn within(self, structure: &str, base: usize) -> Self {
+        match self {
+            Self::Parse {
+                path,
+                offset,
+                source,
+            } => Self::Parse {
+                path: format!("{}/{}", structure, path),
+                offset: offset + base,
+                source: Box::new(source.at_offset(base)),
+            },
+            error => {
+                let error = error.at_offset(base);
+                let offset = match error {
+                    Self::Truncated { offset, .. } => offset,
+                    _ => base,
+                };
+                Self::Parse {
+                    path: structure.to_owned(),
+                    offset,
+                    source: Box::new(error),
+                }
+            }
+        }
+    }
+
+    /// Move the offset of a truncated structure by `base`.
+    fn at_offset(mut self, base: usize) -> Self {
+        if let Self::Truncated { offset, .. } = &mut self {
+            *offset += base;
+        }
+        self
+    }
+
+    /// The structure path of a parse error, such as `LinkTargetIDList/ItemID[3]`.
+    pub fn structure_path(&self) -> Option<&str> {
+        match self {
+            Self::Parse { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The offset of the malformed data of a parse error, from the start of the link data.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Parse { offset, .. } | Self::Truncated { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The error without the location added by [`Parse`](Self::Parse), to match on.
+    pub fn kind(&self) -> &MSLinkError {
+        match self {
+            Self::Parse { source, .. } => source.kind(),
+            error => error,
+        }
+    }
+
+    /// The target `path` can't be stored in a link.
+    pub(crate) fn unsupported_path(path: &str, reason: &str) -> Self {
+        Self::UnsupportedPath {
+            path: path.to_owned(),
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+impl Error for MSLinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for MSLinkError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{}", self.kind, self.message)
+        match self {
+            Self::Io(error) => write!(f, "io:{}", error),
+            Self::InvalidHeader => write!(
+                f,
+                "parse:not a shell link, the header size or CLSID is wrong"
+            ),
+            Self::Truncated { structure, .. } => write!(f, "parse:{} is truncated", structure),
+            Self::StringTooLong { field, len } => write!(
+                f,
+                "size:{} StringData would be {} characters long, more than the limit of {}",
+                field,
+                len,
+                u16::MAX
+            ),
+            Self::TooLarge {
+                structure,
+                size,
+                limit,
+            } => write!(
+                f,
+                "size:{} would be {} long, more than the limit of {}",
+                structure, size, limit
+            ),
+            Self::SizeMismatch {
+                structure,
+                size,
+                expected,
+            } => write!(
+                f,
+                "size:{} is {} long, but should be {}",
+                structure, size, expected
+            ),
+            Self::UnsupportedPath { path, reason } => write!(f, "path:{:?} {}", path, reason),
+            Self::InvalidFlags(message) => write!(f, "flags:{}", message),
+            Self::InvalidValue { field, value } => {
+                write!(f, "parse:unknown {} 0x{:x}", field, value)
+            }
+            Self::InvalidGuid(guid) => write!(f, "parse:{:?} is not a GUID", guid),
+            Self::TrailingData { len } => {
+                write!(f, "parse:{} bytes follow the end of the link", len)
+            }
+            Self::UnknownBlock { signature } => write!(
+                f,
+                "parse:invalid extra data block signature 0x{:08x}",
+                signature
+            ),
+            Self::Parse {
+                path,
+                offset,
+                source,
+            } => write!(f, "{}, in {} at offset 0x{:x}", source, path, offset),
+        }
     }
 }
 
-// Implement std::convert::From for AppError; from io::Error
 impl From<io::Error> for MSLinkError {
     fn from(error: io::Error) -> Self {
-        MSLinkError {
-            kind: String::from("io"),
-            message: error.to_string(),
-        }
+        Self::Io(error)
     }
 }
 
-// Implement std::convert::From for AppError; from num::ParseIntError
+/// A number that failed to parse has no value, so `value` is 0 and `field` describes the error.
 impl From<num::ParseIntError> for MSLinkError {
     fn from(error: num::ParseIntError) -> Self {
-        MSLinkError {
-            kind: String::from("parse"),
-            message: error.to_string(),
+        Self::InvalidValue {
+            field: error.to_string(),
+            value: 0,
         }
     }
 }
+
+#[test]
+fn test_error_source() {
+    let error = MSLinkError::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+    assert!(error.source().is_some());
+    assert_eq!(error.to_string(), "io:gone");
+
+    let error = MSLinkError::from("0x1f".parse::<u8>().unwrap_err());
+    assert!(matches!(error, MSLinkError::InvalidValue { value: 0, .. }));
+
+    let error = MSLinkError::truncated("VolumeLabel")
+        .within("VolumeLabel", 0x10)
+        .within("VolumeID", 0x1c)
+        .within("LinkInfo", 0x60);
+    assert!(error.source().is_some());
+    assert_eq!(
+        error.structure_path(),
+        Some("LinkInfo/VolumeID/VolumeLabel")
+    );
+    assert_eq!(error.offset(), Some(0x8c));
+    assert!(matches!(
+        error.kind(),
+        MSLinkError::Truncated { offset: 0x8c, .. }
+    ));
+    assert_eq!(
+        error.to_string(),
+        "parse:VolumeLabel is truncated, in LinkInfo/VolumeID/VolumeLabel at offset 0x8c"
+    );
+}
@@ -0,0 +1,537 @@
+use crate::{stringdata, MSLinkError};
+use byteorder::{ByteOrder, LE};
+use std::convert::TryFrom;
+
+/// The signature of the extension block of file entry items.
+const FILE_ENTRY_SIGNATURE: u32 = 0xbeef0004;
+
+/// The oldest Windows version a generated link is written for, which selects the version of
+/// the 0xBEEF0004 extension blocks in its file entry items.
+///
+/// Later Windows versions read the blocks of earlier ones, but not the other way round.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WindowsVersion {
+    /// Windows XP, version 3 blocks holding the timestamps and long name.
+    WindowsXp,
+    /// Windows Vista, version 7 blocks adding the NTFS file reference.
+    WindowsVista,
+    /// Windows 7, version 8 blocks. The default.
+    #[default]
+    Windows7,
+    /// Windows 8 and later, including Windows 10 and 11, version 9 blocks.
+    Windows8,
+}
+
+impl WindowsVersion {
+    /// The version of the 0xBEEF0004 extension blocks written for this Windows version.
+    pub fn extension_version(self) -> u16 {
+        match self {
+            Self::WindowsXp => 3,
+            Self::WindowsVista => 7,
+            Self::Windows7 => 8,
+            Self::Windows8 => 9,
+        }
+    }
+
+    /// The Windows version writing extension blocks of `version`, if it is one of these.
+    pub(crate) fn from_extension_version(version: u16) -> Option<Self> {
+        match version {
+            3 => Some(Self::WindowsXp),
+            7 => Some(Self::WindowsVista),
+            8 => Some(Self::Windows7),
+            9 => Some(Self::Windows8),
+            _ => None,
+        }
+    }
+
+    /// The version identifier stored in the blocks written by this Windows version.
+    fn version_id(self) -> u16 {
+        match self {
+            Self::WindowsXp => 0x14,
+            Self::WindowsVista => 0x26,
+            Self::Windows7 => 0x2a,
+            Self::Windows8 => 0x2e,
+        }
+    }
+}
+
+/// An extension block, appended to shell items such as file entries to store data that
+/// doesn't fit their original layout.
+#[derive(Clone, Debug)]
+pub enum ExtensionBlock {
+    /// The 0xBEEF0004 block of a file entry item, holding its long name and timestamps.
+    FileEntry(FileEntryExtension),
+    /// Any other block, kept as read. Its size is computed when it is written.
+    Unknown {
+        /// The signature of the block.
+        signature: u32,
+        /// The version of the block.
+        version: u16,
+        /// The data following the signature, up to the end of the block.
+        data: Vec<u8>,
+    },
+}
+
+impl ExtensionBlock {
+    /// The signature of the block, such as 0xBEEF0004.
+    pub fn signature(&self) -> u32 {
+        match self {
+            Self::FileEntry(_) => FILE_ENTRY_SIGNATURE,
+            Self::Unknown { signature, .. } => *signature,
+        }
+    }
+
+    /// The version of the block.
+    pub fn version(&self) -> u16 {
+        match self {
+            Self::FileEntry(block) => block.version,
+            Self::Unknown { version, .. } => *version,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for ExtensionBlock {
+    type Error = MSLinkError;
+
+    /// Read a block, starting at its size field. The data may extend beyond the block.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = || MSLinkError::truncated("ExtensionBlock");
+        if data.len() < 8 {
+            return Err(truncated());
+        }
+        let size = LE::read_u16(data) as usize;
+        let block = data
+            .get(..size)
+            .filter(|_| size >= 8)
+            .ok_or_else(truncated)?;
+        if LE::read_u32(&block[4..]) == FILE_ENTRY_SIGNATURE {
+            if let Ok(block) = FileEntryExtension::try_from(block) {
+                return Ok(Self::FileEntry(block));
+            }
+        }
+        Ok(Self::Unknown {
+            signature: LE::read_u32(&block[4..]),
+            version: LE::read_u16(&block[2..]),
+            data: block[8..].to_vec(),
+        })
+    }
+}
+
+impl From<ExtensionBlock> for Vec<u8> {
+    fn from(val: ExtensionBlock) -> Self {
+        match val {
+            ExtensionBlock::FileEntry(block) => block.into(),
+            ExtensionBlock::Unknown {
+                signature,
+                version,
+                mut data,
+            } => {
+                let mut block = vec![0u8; 8];
+                LE::write_u16(&mut block, (8 + data.len()) as u16);
+                LE::write_u16(&mut block[2..], version);
+                LE::write_u32(&mut block[4..], signature);
+                block.append(&mut data);
+                block
+            }
+        }
+    }
+}
+
+/// Read the extension blocks filling `data`. Returns the blocks and the data following the
+/// last block that could be read.
+pub(crate) fn parse_blocks(mut data: &[u8]) -> (Vec<ExtensionBlock>, &[u8]) {
+    let mut blocks = vec![];
+    while let Ok(block) = ExtensionBlock::try_from(data) {
+        data = &data[LE::read_u16(data) as usize..];
+        blocks.push(block);
+    }
+    (blocks, data)
+}
+
+/// The 0xBEEF0004 extension block of a file entry item.
+///
+/// Its layout depends on the version: version 3 (Windows XP) holds the timestamps and long
+/// name, version 7 (Vista) adds the NTFS file reference, versions 8 (Windows 7) and 9
+/// (Windows 8 and later) add unknown fields before the long name.
+#[derive(Clone, Debug)]
+pub struct FileEntryExtension {
+    version: u16,
+    /// The creation time as FAT date and time.
+    creation_time: u32,
+    /// The last access time as FAT date and time.
+    access_time: u32,
+    /// Identifies the Windows version that wrote the block, e.g. 0x14 for Windows XP.
+    version_id: u16,
+    /// The fields between the version identifier and the long name, kept as read.
+    fields: Vec<u8>,
+    long_name: String,
+    localized_name: Option<String>,
+    /// The offset of the first extension block within the item.
+    first_offset: u16,
+}
+
+impl FileEntryExtension {
+    /// Create a block holding `long_name` as written by `windows_version`, for an item whose
+    /// extension blocks start at `first_offset`. The fields between the version identifier
+    /// and the long name are zero.
+    pub(crate) fn new(windows_version: WindowsVersion, long_name: &str, first_offset: u16) -> Self {
+        let version = windows_version.extension_version();
+        Self {
+            version,
+            creation_time: 0,
+            access_time: 0,
+            version_id: windows_version.version_id(),
+            fields: vec![0; Self::long_name_offset(version) - 18],
+            long_name: long_name.to_owned(),
+            localized_name: None,
+            first_offset,
+        }
+    }
+
+    /// Change the block to the layout written by `windows_version`. The timestamps, names and
+    /// NTFS file reference are kept, the long string size is recomputed and the other fields
+    /// between the version identifier and the long name are zero.
+    pub(crate) fn set_windows_version(&mut self, windows_version: WindowsVersion) {
+        let ntfs_reference = self.ntfs_reference();
+        self.version = windows_version.extension_version();
+        self.version_id = windows_version.version_id();
+        // the layouts place their fields at different offsets, so they are rebuilt
+        let mut fields = vec![0; Self::long_name_offset(self.version) - 18];
+        let long_string_size = self.long_string_size();
+        if self.version >= 7 {
+            LE::write_u64(&mut fields[2..], ntfs_reference.unwrap_or_default());
+            LE::write_u16(&mut fields[18..], long_string_size);
+        } else {
+            LE::write_u16(&mut fields, long_string_size);
+        }
+        self.fields = fields;
+    }
+
+    /// The long string size field: the size of the long and localized names including their
+    /// terminating NULLs, or 0 if there is no localized name.
+    fn long_string_size(&self) -> u16 {
+        let localized_name = match &self.localized_name {
+            Some(name) if Self::unicode_localized_name(self.version) => {
+                stringdata::to_unicode_z(name)
+            }
+            Some(name) => stringdata::to_ansi_z(name),
+            None => return 0,
+        };
+        (self.long_name.encode_utf16().count() * 2 + 2 + localized_name.len()) as u16
+    }
+
+    /// Set the creation and last access times, as FAT date and time.
+    pub(crate) fn set_times(&mut self, creation_time: u32, access_time: u32) {
+        self.creation_time = creation_time;
+        self.access_time = access_time;
+    }
+
+    /// The offset of the long name in blocks of `version`, as specified.
+    fn long_name_offset(version: u16) -> usize {
+        match version {
+            0..=6 => 20,
+            7 => 38,
+            8 => 42,
+            _ => 46,
+        }
+    }
+
+    /// Where the long name is found in blocks of `version` whose writers didn't follow the
+    /// specification: older versions of this crate wrote version 8 blocks without the fields
+    /// of version 7, and a version 9 layout without the fields of version 8 has been seen in
+    /// links written by Windows 10.
+    fn misplaced_long_name_offset(version: u16) -> Option<usize> {
+        match version {
+            8 => Some(22),
+            9 => Some(38),
+            _ => None,
+        }
+    }
+
+    /// The version of the block, 3, 7, 8 or 9.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// The creation time as FAT date (low 16 bits) and time (high 16 bits).
+    pub fn creation_time(&self) -> u32 {
+        self.creation_time
+    }
+
+    /// The last access time as FAT date (low 16 bits) and time (high 16 bits).
+    pub fn access_time(&self) -> u32 {
+        self.access_time
+    }
+
+    /// Identifies the Windows version that wrote the block: 0x14 Windows XP, 0x26 Vista,
+    /// 0x2a Windows 7, 0x2e Windows 8 and later.
+    pub fn version_id(&self) -> u16 {
+        self.version_id
+    }
+
+    /// The NTFS file reference of the entry, holding the MFT entry number in its low 48 bits
+    /// and the sequence number in its high 16 bits. Present from version 7.
+    pub fn ntfs_reference(&self) -> Option<u64> {
+        match self.version {
+            7..=u16::MAX => self.fields.get(2..10).map(LE::read_u64),
+            _ => None,
+        }
+    }
+
+    /// The long name of the entry.
+    pub fn long_name(&self) -> &str {
+        &self.long_name
+    }
+
+    /// The localized name of the entry, such as `Documents` for a folder named `Dokumente`
+    /// on German systems, if any.
+    pub fn localized_name(&self) -> Option<&str> {
+        self.localized_name.as_deref()
+    }
+
+    /// The offset of the first extension block within its item, counted from the item's
+    /// ItemIDSize field.
+    pub fn first_offset(&self) -> u16 {
+        self.first_offset
+    }
+
+    /// Whether the localized name is stored in Unicode, as it is from version 7.
+    fn unicode_localized_name(version: u16) -> bool {
+        version >= 7
+    }
+}
+
+impl TryFrom<&[u8]> for FileEntryExtension {
+    type Error = MSLinkError;
+
+    /// Read the block, starting at its size field and ending with it.
+    ///
+    /// Some writers, including older versions of this crate, don't place the long name where
+    /// the version says, see `misplaced_long_name_offset`. The long name is taken from the
+    /// offset whose strings end exactly at the end of the block.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = || MSLinkError::truncated("FileEntryExtension");
+        if data.len() < 20 {
+            return Err(truncated());
+        }
+        let version = LE::read_u16(&data[2..]);
+        // the block ends with the offset of the first extension block
+        let end = data.len() - 2;
+        let specified = Self::long_name_offset(version);
+        let candidates = [Some(specified), Self::misplaced_long_name_offset(version)];
+        let names_at = |offset: usize| {
+            let names = data.get(offset..end)?;
+            let long_name = stringdata::parse_unicode_z(names, "FileEntryExtension").ok()?;
+            if long_name.is_empty() {
+                return None;
+            }
+            let rest = &names[long_name.encode_utf16().count() * 2 + 2..];
+            if rest.is_empty() {
+                return Some((offset, long_name, None));
+            }
+            let (localized_name, len) = if Self::unicode_localized_name(version) {
+                let name = stringdata::parse_unicode_z(rest, "FileEntryExtension").ok()?;
+                let len = name.encode_utf16().count() * 2 + 2;
+                (name, len)
+            } else {
+                let (len, name) = stringdata::parse_ansi_z(rest, "FileEntryExtension").ok()?;
+                (name, len)
+            };
+            if len != rest.len() {
+                return None;
+            }
+            Some((offset, long_name, Some(localized_name)))
+        };
+        // an offset within the long name also yields strings ending at the end of the block,
+        // so offsets following a zero field are preferred
+        let (name_offset, long_name, localized_name) = candidates
+            .iter()
+            .flatten()
+            .filter(|&&offset| {
+                data.get(offset - 2..offset)
+                    .is_some_and(|field| LE::read_u16(field) == 0)
+            })
+            .find_map(|&offset| names_at(offset))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .flatten()
+                    .find_map(|&offset| names_at(offset))
+            })
+            .ok_or_else(truncated)?;
+        Ok(Self {
+            version,
+            creation_time: LE::read_u32(&data[8..]),
+            access_time: LE::read_u32(&data[12..]),
+            version_id: LE::read_u16(&data[16..]),
+            fields: data[18..name_offset].to_vec(),
+            long_name,
+            localized_name,
+            first_offset: LE::read_u16(&data[end..]),
+        })
+    }
+}
+
+impl From<FileEntryExtension> for Vec<u8> {
+    fn from(val: FileEntryExtension) -> Self {
+        let mut data = vec![0u8; 18];
+        LE::write_u16(&mut data[2..4], val.version);
+        LE::write_u32(&mut data[4..8], FILE_ENTRY_SIGNATURE);
+        LE::write_u32(&mut data[8..12], val.creation_time);
+        LE::write_u32(&mut data[12..16], val.access_time);
+        LE::write_u16(&mut data[16..18], val.version_id);
+        data.extend_from_slice(&val.fields);
+        data.append(&mut stringdata::to_unicode_z(&val.long_name));
+        if let Some(localized_name) = &val.localized_name {
+            if FileEntryExtension::unicode_localized_name(val.version) {
+                data.append(&mut stringdata::to_unicode_z(localized_name));
+            } else {
+                data.append(&mut stringdata::to_ansi_z(localized_name));
+            }
+        }
+        data.extend_from_slice(&val.first_offset.to_le_bytes());
+        // a block larger than 64 KiB also makes its item too large
+        let size = data.len() as u16;
+        LE::write_u16(&mut data[0..2], size);
+        data
+    }
+}
+
+#[cfg(test)]
+fn file_entry_block(version: u16, fields: &[u8], names: &[u8]) -> Vec<u8> {
+    let mut data = vec![0u8; 18];
+    LE::write_u16(&mut data[2..], version);
+    LE::write_u32(&mut data[4..], FILE_ENTRY_SIGNATURE);
+    LE::write_u32(&mut data[8..], 0x5678_1234);
+    LE::write_u32(&mut data[12..], 0x5679_1234);
+    LE::write_u16(&mut data[16..], 0x2e);
+    data.extend_from_slice(fields);
+    data.extend_from_slice(names);
+    data.extend_from_slice(&0x14u16.to_le_bytes());
+    let size = data.len() as u16;
+    LE::write_u16(&mut data, size);
+    data
+}
+
+#[test]
+fn test_file_entry_extension() {
+    let mut ntfs_fields = vec![0u8; 20];
+    LE::write_u64(&mut ntfs_fields[2..], 0x0003_0000_0000_1234);
+    let long_name = stringdata::to_unicode_z("Dokumente");
+    let localized = stringdata::to_unicode_z("Documents");
+    // version, fields before the long name, names, localized name
+    let cases = vec![
+        (3, vec![0; 2], long_name.clone(), None),
+        (
+            3,
+            vec![0; 2],
+            [&long_name[..], b"Documents\0"].concat(),
+            Some("Documents"),
+        ),
+        // a localized name in the system code page with a character outside ASCII
+        (
+            3,
+            vec![0; 2],
+            [&long_name[..], b"Dokument\xe9\0"].concat(),
+            Some("Dokument\u{e9}"),
+        ),
+        (
+            7,
+            ntfs_fields.clone(),
+            [&long_name[..], &localized].concat(),
+            Some("Documents"),
+        ),
+        (
+            8,
+            [&ntfs_fields[..], &[0; 4]].concat(),
+            long_name.clone(),
+            None,
+        ),
+        (
+            9,
+            [&ntfs_fields[..], &[0; 8]].concat(),
+            long_name.clone(),
+            None,
+        ),
+        // as written by older versions of this crate
+        (8, vec![0; 4], long_name.clone(), None),
+        // as seen in links written by Windows 10
+        (9, ntfs_fields.clone(), long_name.clone(), None),
+    ];
+    for (version, fields, names, localized_name) in cases {
+        let data = file_entry_block(version, &fields, &names);
+        let block = match ExtensionBlock::try_from(&data[..]).unwrap() {
+            ExtensionBlock::FileEntry(block) => block,
+            block => panic!("version {} not parsed: {:?}", version, block),
+        };
+        assert_eq!(block.version(), version);
+        assert_eq!(block.creation_time(), 0x5678_1234);
+        assert_eq!(block.access_time(), 0x5679_1234);
+        assert_eq!(block.version_id(), 0x2e);
+        assert_eq!(block.long_name(), "Dokumente", "version {}", version);
+        assert_eq!(block.localized_name(), localized_name);
+        if version >= 7 && fields.len() >= 10 {
+            assert_eq!(block.ntfs_reference(), Some(0x0003_0000_0000_1234));
+        }
+        assert_eq!(Vec::<u8>::from(block), data);
+    }
+
+    // changing the layout keeps the names, times and NTFS file reference
+    let data = file_entry_block(7, &ntfs_fields, &[&long_name[..], &localized].concat());
+    let mut block = FileEntryExtension::try_from(&data[..]).unwrap();
+    block.set_windows_version(WindowsVersion::Windows8);
+    let data: Vec<u8> = block.into();
+    let block = FileEntryExtension::try_from(&data[..]).unwrap();
+    assert_eq!((block.version(), block.version_id()), (9, 0x2e));
+    assert_eq!(block.ntfs_reference(), Some(0x0003_0000_0000_1234));
+    assert_eq!(block.long_name(), "Dokumente");
+    assert_eq!(block.localized_name(), Some("Documents"));
+    assert_eq!(block.access_time(), 0x5679_1234);
+
+    // the long string size moves between versions 3 and 7, the NTFS file reference is dropped
+    let names = [&long_name[..], b"Documents\0"].concat();
+    let data = file_entry_block(3, &[0; 2], &names);
+    let mut block = FileEntryExtension::try_from(&data[..]).unwrap();
+    block.set_windows_version(WindowsVersion::WindowsVista);
+    let data: Vec<u8> = block.into();
+    assert_eq!(LE::read_u16(&data[18..]), 0);
+    assert_eq!(LE::read_u64(&data[20..]), 0);
+    assert_eq!(
+        LE::read_u16(&data[36..]) as usize,
+        long_name.len() + localized.len()
+    );
+    let mut block = FileEntryExtension::try_from(&data[..]).unwrap();
+    assert_eq!(block.version(), 7);
+    assert_eq!(block.long_name(), "Dokumente");
+    assert_eq!(block.localized_name(), Some("Documents"));
+    block.set_windows_version(WindowsVersion::WindowsXp);
+    let data: Vec<u8> = block.into();
+    let mut expected = file_entry_block(3, &[0x1e, 0], &names);
+    LE::write_u16(&mut expected[16..], 0x14);
+    assert_eq!(data, expected);
+    let block = FileEntryExtension::try_from(&data[..]).unwrap();
+    assert_eq!(block.localized_name(), Some("Documents"));
+    assert_eq!(block.ntfs_reference(), None);
+
+    // other blocks are kept as read, and the data after the blocks is returned
+    let mut data = file_entry_block(9, &[0; 28], &long_name);
+    data.extend_from_slice(&[10, 0, 1, 0, 0x05, 0, 0xef, 0xbe, 0xaa, 0xbb, 0, 0]);
+    let (blocks, rest) = parse_blocks(&data);
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[1].signature(), 0xbeef0005);
+    assert_eq!(blocks[1].version(), 1);
+    assert_eq!(rest, [0, 0]);
+    assert_eq!(
+        Vec::<u8>::from(blocks[1].clone()),
+        data[data.len() - 12..][..10]
+    );
+
+    // blocks built by callers get a matching size
+    let block = ExtensionBlock::Unknown {
+        signature: 0xbeef0026,
+        version: 1,
+        data: vec![],
+    };
+    assert_eq!(block.signature(), 0xbeef0026);
+    assert_eq!(Vec::<u8>::from(block), [8, 0, 1, 0, 0x26, 0, 0xef, 0xbe]);
+}
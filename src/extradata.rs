@@ -1,11 +1,15 @@
+use crate::linkinfo::MAX_PATH;
+use crate::read::Issues;
+use crate::{LinkFlags, MSLinkError};
 use byteorder::{ByteOrder, LE};
-#[allow(unused)]
+use std::convert::TryFrom;
 
 /// ExtraData refers to a set of structures that convey additional information
 /// about a link target. These optional structures can be present in an extra
 /// data section that is appended to the basic Shell Link Binary File Format.
 ///
-/// At the moment, ExtraData can only be read, not written to shortcuts.
+/// The block contents are kept as raw bytes, without the BlockSize and
+/// BlockSignature fields.
 #[allow(missing_docs)]
 #[derive(Clone, Debug)]
 pub enum ExtraData {
@@ -20,15 +24,128 @@ pub enum ExtraData {
     SpecialFolderProps(Vec<u8>),
     TrackerProps(Vec<u8>),
     VistaAndAboveIdListProps(Vec<u8>),
+    /// A block with a signature not defined by the specification, with its signature.
+    Unknown(u32, Vec<u8>),
 }
 
-impl From<&[u8]> for ExtraData {
-    fn from(data: &[u8]) -> Self {
+impl ExtraData {
+    /// Create an EnvironmentVariableDataBlock, holding a path to the link target that may
+    /// contain environment variables such as `%ProgramFiles%\\app\\app.exe`.
+    ///
+    /// Both copies of the path are stored in fixed MAX_PATH sized fields, so longer targets
+    /// are rejected.
+    pub fn environment_variables(target: &str) -> Result<Self, MSLinkError> {
+        let unicode: Vec<u16> = target.encode_utf16().collect();
+        // room for the terminating NULL
+        if unicode.len() >= MAX_PATH {
+            return Err(MSLinkError::too_large(
+                "EnvironmentVariableDataBlock",
+                unicode.len() + 1,
+                MAX_PATH,
+            ));
+        }
+        // TargetAnsi followed by TargetUnicode
+        let mut data = vec![0u8; MAX_PATH * 3];
+        for (i, c) in target.chars().enumerate() {
+            // FIXME: clips non-Latin-1 characters, see `stringdata::to_data`
+            data[i] = c as u8;
+        }
+        LE::write_u16_into(&unicode, &mut data[MAX_PATH..MAX_PATH + unicode.len() * 2]);
+        Ok(Self::EnvironmentProps(data))
+    }
+
+    /// The BlockSignature of this block.
+    pub fn signature(&self) -> u32 {
+        match self {
+            Self::ConsoleProps(_) => 0xa0000002,
+            Self::ConsoleFeProps(_) => 0xa0000004,
+            Self::DarwinProps(_) => 0xa0000006,
+            Self::EnvironmentProps(_) => 0xa0000001,
+            Self::IconEnvironmentProps(_) => 0xa0000007,
+            Self::KnownFolderProps(_) => 0xa000000b,
+            Self::PropertyStoreProps(_) => 0xa0000009,
+            Self::ShimProps(_) => 0xa0000008,
+            Self::SpecialFolderProps(_) => 0xa0000005,
+            Self::TrackerProps(_) => 0xa0000003,
+            Self::VistaAndAboveIdListProps(_) => 0xa000000a,
+            Self::Unknown(signature, _) => *signature,
+        }
+    }
+
+    /// The LinkFlags bit announcing this block, for the blocks that have one.
+    pub fn link_flag(&self) -> Option<LinkFlags> {
+        match self {
+            Self::DarwinProps(_) => Some(LinkFlags::HAS_DARWIN_ID),
+            Self::EnvironmentProps(_) => Some(LinkFlags::HAS_EXP_STRING),
+            Self::IconEnvironmentProps(_) => Some(LinkFlags::HAS_EXP_ICON),
+            Self::ShimProps(_) => Some(LinkFlags::RUN_WITH_SHIM_LAYER),
+            _ => None,
+        }
+    }
+
+    /// The BlockSize of the blocks that have a fixed size.
+    fn fixed_size(&self) -> Option<usize> {
+        match self {
+            Self::ConsoleProps(_) => Some(0xcc),
+            Self::ConsoleFeProps(_) => Some(0x0c),
+            Self::DarwinProps(_) | Self::EnvironmentProps(_) | Self::IconEnvironmentProps(_) => {
+                Some(0x314)
+            }
+            Self::KnownFolderProps(_) => Some(0x1c),
+            Self::SpecialFolderProps(_) => Some(0x10),
+            Self::TrackerProps(_) => Some(0x60),
+            Self::PropertyStoreProps(_)
+            | Self::ShimProps(_)
+            | Self::VistaAndAboveIdListProps(_)
+            | Self::Unknown(..) => None,
+        }
+    }
+
+    /// Check that a fixed size block holds exactly as much data as its BlockSize requires.
+    pub(crate) fn check_size(&self) -> Result<(), MSLinkError> {
+        let size = 8 + self.data().len();
+        match self.fixed_size() {
+            Some(fixed_size) if size != fixed_size => Err(MSLinkError::SizeMismatch {
+                structure: format!("extra data block 0x{:08x}", self.signature()),
+                size,
+                expected: fixed_size,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The block contents, without the BlockSize and BlockSignature fields.
+    pub fn data(&self) -> &[u8] {
+        match self {
+            Self::ConsoleProps(data)
+            | Self::ConsoleFeProps(data)
+            | Self::DarwinProps(data)
+            | Self::EnvironmentProps(data)
+            | Self::IconEnvironmentProps(data)
+            | Self::KnownFolderProps(data)
+            | Self::PropertyStoreProps(data)
+            | Self::ShimProps(data)
+            | Self::SpecialFolderProps(data)
+            | Self::TrackerProps(data)
+            | Self::VistaAndAboveIdListProps(data)
+            | Self::Unknown(_, data) => data,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for ExtraData {
+    type Error = MSLinkError;
+
+    /// Read a single block, `data` must hold exactly the block.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(MSLinkError::truncated("ExtraData"));
+        }
         let _size = LE::read_u32(data) as usize;
         let sig = LE::read_u32(&data[4..]);
         let data = &data[8..];
 
-        match sig {
+        Ok(match sig {
             0xa0000002 => Self::ConsoleProps(data.to_vec()),
             0xa0000004 => Self::ConsoleFeProps(data.to_vec()),
             0xa0000006 => Self::DarwinProps(data.to_vec()),
@@ -40,7 +157,93 @@ impl From<&[u8]> for ExtraData {
             0xa0000005 => Self::SpecialFolderProps(data.to_vec()),
             0xa0000003 => Self::TrackerProps(data.to_vec()),
             0xa000000a => Self::VistaAndAboveIdListProps(data.to_vec()),
-            _ => panic!("Invalid extra data type!"),
+            _ => Self::Unknown(sig, data.to_vec()),
+        })
+    }
+}
+
+impl From<ExtraData> for Vec<u8> {
+    fn from(val: ExtraData) -> Self {
+        let mut data = vec![0u8; 8];
+        LE::write_u32(&mut data[0..4], 8 + val.data().len() as u32);
+        LE::write_u32(&mut data[4..8], val.signature());
+        data.extend_from_slice(val.data());
+        data
+    }
+}
+
+/// Read the extra data blocks up to the TerminalBlock, from `data` found at offset `base` of
+/// the link. The TerminalBlock and any data after it are returned as well, unless a damaged
+/// block hides where they start.
+pub(crate) fn parse_blocks(
+    mut data: &[u8],
+    base: usize,
+    issues: &mut Issues,
+) -> Result<(Vec<ExtraData>, Option<Vec<u8>>), MSLinkError> {
+    let mut blocks = vec![];
+    let mut offset = 0;
+    let mut index = 0;
+    let within = |error: MSLinkError, path: &str, offset: usize| {
+        error.within(path, offset).within("ExtraData", base)
+    };
+    loop {
+        // a missing TerminalBlock is only a violation, as written by older versions of this
+        // crate
+        if data.len() < 4 {
+            let error = MSLinkError::truncated("TerminalBlock");
+            issues.violation(within(error, "TerminalBlock", offset))?;
+            return Ok((blocks, Some(data.to_vec())));
+        }
+        let size = LE::read_u32(data) as usize;
+        // TerminalBlock
+        if size < 4 {
+            if data.len() > 4 {
+                let error = MSLinkError::TrailingData {
+                    len: data.len() - 4,
+                };
+                issues.violation(within(error, "TerminalBlock", offset + 4))?;
+            }
+            return Ok((blocks, Some(data.to_vec())));
         }
+        // counts skipped blocks too, so paths match the positions in the data
+        let path = format!("Block[{}]", index);
+        let block = match data.get(..size) {
+            Some(block) => block,
+            None => {
+                // the rest of the data can't be split into blocks
+                issues.damaged(within(MSLinkError::truncated("ExtraData"), &path, offset))?;
+                return Ok((blocks, None));
+            }
+        };
+        match ExtraData::try_from(block) {
+            Ok(block) => {
+                if let ExtraData::Unknown(signature, _) = block {
+                    let error = MSLinkError::UnknownBlock { signature };
+                    issues.violation(within(error, &path, offset))?;
+                }
+                if let Err(error) = block.check_size() {
+                    issues.violation(within(error, &path, offset))?;
+                }
+                blocks.push(block);
+            }
+            Err(error) => issues.damaged(within(error, &path, offset))?,
+        }
+        data = &data[size..];
+        offset += size;
+        index += 1;
     }
 }
+
+#[test]
+fn test_environment_variables_block() {
+    let block = ExtraData::environment_variables(r"%ProgramFiles%\app\app.exe").unwrap();
+    let data: Vec<u8> = block.into();
+    assert_eq!(data.len(), 0x314);
+    assert_eq!(LE::read_u32(&data), 0x314);
+    assert_eq!(LE::read_u32(&data[4..]), 0xa0000001);
+    assert!(data[8..].starts_with(b"%ProgramFiles%\\app\\app.exe\0"));
+    assert_eq!(LE::read_u16(&data[8 + MAX_PATH..]), u16::from(b'%'));
+
+    assert!(ExtraData::environment_variables(&"a".repeat(259)).is_ok());
+    assert!(ExtraData::environment_variables(&"a".repeat(260)).is_err());
+}
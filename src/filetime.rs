@@ -0,0 +1,102 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 100-nanosecond intervals per second, the resolution of a FILETIME.
+const INTERVALS_PER_SECOND: u64 = 10_000_000;
+
+/// Seconds from 1601-01-01, where FILETIMEs start, to 1970-01-01.
+const UNIX_EPOCH_SECONDS: u64 = 11_644_473_600;
+
+/// Convert a system time to a FILETIME ([MS-DTYP] section 2.3.3), counting 100-nanosecond
+/// intervals since 1601-01-01 UTC. Times before 1601 are clamped to 0, meaning no time.
+pub(crate) fn from_system_time(time: SystemTime) -> u64 {
+    let intervals = |duration: std::time::Duration| {
+        duration.as_secs() * INTERVALS_PER_SECOND + u64::from(duration.subsec_nanos()) / 100
+    };
+    let epoch = UNIX_EPOCH_SECONDS * INTERVALS_PER_SECOND;
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => epoch + intervals(after),
+        Err(before) => epoch.saturating_sub(intervals(before.duration())),
+    }
+}
+
+/// Convert a FILETIME to the FAT date (low 16 bits) and time (high 16 bits) stored in shell
+/// items, with a resolution of two seconds. Times a FAT date can't hold, before 1980 or after
+/// 2107, and the zero FILETIME convert to 0.
+pub(crate) fn to_fat(filetime: u64) -> u32 {
+    let seconds = match (filetime / INTERVALS_PER_SECOND).checked_sub(UNIX_EPOCH_SECONDS) {
+        Some(seconds) => seconds,
+        None => return 0,
+    };
+    let (year, month, day) = civil_from_days(seconds / 86400);
+    if !(1980..=2107).contains(&year) {
+        return 0;
+    }
+    let time_of_day = seconds % 86400;
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    let time =
+        ((time_of_day / 3600) << 11) | ((time_of_day / 60 % 60) << 5) | (time_of_day % 60 / 2);
+    (time << 16 | date) as u32
+}
+
+/// Format a FILETIME as the `MM/DD/YYYY  HH:MM` time, in UTC, written by the compressed
+/// folder in its items. Times before 1970 and the zero FILETIME format as an empty string.
+pub(crate) fn to_short_date_time(filetime: u64) -> String {
+    let seconds = match (filetime / INTERVALS_PER_SECOND).checked_sub(UNIX_EPOCH_SECONDS) {
+        Some(seconds) => seconds,
+        None => return String::new(),
+    };
+    let (year, month, day) = civil_from_days(seconds / 86400);
+    let time_of_day = seconds % 86400;
+    format!(
+        "{:02}/{:02}/{:04}  {:02}:{:02}",
+        month,
+        day,
+        year,
+        time_of_day / 3600,
+        time_of_day / 60 % 60
+    )
+}
+
+/// The year, month and day of `days` since 1970-01-01, in the proleptic Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // shift to 0000-03-01 so leap days end the 400-year eras and their years
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[test]
+fn test_time_conversions() {
+    use std::time::Duration;
+
+    assert_eq!(from_system_time(UNIX_EPOCH), 116_444_736_000_000_000);
+    // 2008-09-12 20:27:19.5 UTC
+    let time = UNIX_EPOCH + Duration::from_millis(1_221_251_239_500);
+    let filetime = from_system_time(time);
+    assert_eq!(filetime, 128_657_248_395_000_000);
+    assert_eq!(to_fat(filetime), 0xa369_392c);
+    // 2000-02-29 00:00:00 UTC, a leap day
+    assert_eq!(to_fat(125_962_560_000_000_000), 0x0000_285d);
+
+    assert_eq!(to_short_date_time(filetime), "09/12/2008  20:27");
+
+    assert_eq!(to_fat(0), 0);
+    assert_eq!(to_short_date_time(0), "");
+    assert_eq!(to_fat(from_system_time(UNIX_EPOCH)), 0);
+    assert_eq!(
+        from_system_time(UNIX_EPOCH - Duration::from_secs(UNIX_EPOCH_SECONDS + 1)),
+        0
+    );
+}
@@ -0,0 +1,123 @@
+use crate::MSLinkError;
+use byteorder::{ByteOrder, LE};
+use std::fmt;
+use std::str::FromStr;
+
+/// A GUID, such as the CLSID of a shell folder, stored in the mixed-endian layout used by
+/// Windows: the first three fields little-endian, the last eight bytes as they are.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Guid([u8; 16]);
+
+impl Guid {
+    /// Create a GUID from its fields, so that `{20D04FE0-3AEA-1069-A2D8-08002B30309D}` is
+    /// `Guid::from_fields(0x20D04FE0, 0x3AEA, 0x1069, [0xA2, 0xD8, 0x08, 0x00, 0x2B, 0x30,
+    /// 0x30, 0x9D])`.
+    pub const fn from_fields(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        let a = data1.to_le_bytes();
+        let b = data2.to_le_bytes();
+        let c = data3.to_le_bytes();
+        Self([
+            a[0], a[1], a[2], a[3], b[0], b[1], c[0], c[1], data4[0], data4[1], data4[2], data4[3],
+            data4[4], data4[5], data4[6], data4[7],
+        ])
+    }
+
+    /// Create a GUID from its 16 bytes as stored in a link.
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    /// Read a GUID from the first 16 bytes of `data`, if there are as many.
+    pub(crate) fn read(data: &[u8]) -> Option<Self> {
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(data.get(..16)?);
+        Some(Self(bytes))
+    }
+
+    /// The 16 bytes of the GUID as stored in a link.
+    pub fn to_bytes(self) -> [u8; 16] {
+        self.0
+    }
+}
+
+impl fmt::Display for Guid {
+    /// Format the GUID in registry format, e.g. `{20D04FE0-3AEA-1069-A2D8-08002B30309D}`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+            LE::read_u32(&b[0..4]),
+            LE::read_u16(&b[4..6]),
+            LE::read_u16(&b[6..8]),
+            b[8],
+            b[9],
+            b[10],
+            b[11],
+            b[12],
+            b[13],
+            b[14],
+            b[15]
+        )
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for Guid {
+    type Err = MSLinkError;
+
+    /// Parse a GUID in registry format, with or without braces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MSLinkError::InvalidGuid(s.to_owned());
+        let hex = s
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .unwrap_or(s);
+        let groups: Vec<&str> = hex.split('-').collect();
+        if groups.iter().map(|g| g.len()).collect::<Vec<_>>() != [8, 4, 4, 4, 12]
+            || !hex.chars().all(|c| c == '-' || c.is_ascii_hexdigit())
+        {
+            return Err(invalid());
+        }
+        let data1 = u32::from_str_radix(groups[0], 16).map_err(|_| invalid())?;
+        let data2 = u16::from_str_radix(groups[1], 16).map_err(|_| invalid())?;
+        let data3 = u16::from_str_radix(groups[2], 16).map_err(|_| invalid())?;
+        let tail = format!("{}{}", groups[3], groups[4]);
+        let mut data4 = [0; 8];
+        for (i, byte) in data4.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&tail[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self::from_fields(data1, data2, data3, data4))
+    }
+}
+
+#[test]
+fn test_guid_format() {
+    let guid = Guid::from_fields(
+        0x20D04FE0,
+        0x3AEA,
+        0x1069,
+        [0xA2, 0xD8, 0x08, 0x00, 0x2B, 0x30, 0x30, 0x9D],
+    );
+    assert_eq!(
+        guid.to_bytes()[..4],
+        [0xE0, 0x4F, 0xD0, 0x20],
+        "the first field is little-endian"
+    );
+    assert_eq!(guid.to_string(), "{20D04FE0-3AEA-1069-A2D8-08002B30309D}");
+    assert_eq!(
+        "20d04fe0-3aea-1069-a2d8-08002b30309d"
+            .parse::<Guid>()
+            .unwrap(),
+        guid
+    );
+    assert!("{20D04FE0-3AEA-1069-A2D8}".parse::<Guid>().is_err());
+    assert!("{20D04FE0-3AEA-1069-A2D8-08002B30309G}"
+        .parse::<Guid>()
+        .is_err());
+}
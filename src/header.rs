@@ -1,7 +1,9 @@
+use crate::MSLinkError;
 use bitflags::bitflags;
 use byteorder::{ByteOrder, LE};
+use std::convert::TryFrom;
 
-const CLSID: u128 = 0x460000000000_00c0_0000_0000_00021401;
+const CLSID: u128 = 0x4600_0000_0000_00c0_0000_0000_0002_1401;
 
 /// A ShellLinkHeader structure (section 2.1), which contains identification
 /// information, timestamps, and flags that specify the presence of optional
@@ -39,6 +41,13 @@ pub struct ShellLinkHeader {
     /// application referenced by the shortcut key. This value is assigned to the application after
     /// it is launched, so that pressing the key activates that application.
     hotkey: HotkeyFlags,
+    /// The ShowCommand and HotkeyFlags fields as read. They are written back as long as they
+    /// still decode to `show_command` and `hotkey`, so values that can't be represented by
+    /// those survive reading and writing a link.
+    raw_show_command: Option<u32>,
+    raw_hotkey: Option<u16>,
+    /// The Reserved1, Reserved2 and Reserved3 fields, which are zero in valid links.
+    reserved: [u8; 10],
 }
 
 impl ShellLinkHeader {
@@ -153,32 +162,78 @@ impl Default for ShellLinkHeader {
             icon_index: 0,
             show_command: ShowCommand::ShowNormal,
             hotkey: HotkeyFlags::new(HotkeyKey::NoKeyAssigned, HotkeyModifiers::NO_MODIFIER),
+            raw_show_command: None,
+            raw_hotkey: None,
+            reserved: [0; 10],
         }
     }
 }
 
-impl Into<[u8; 0x4c]> for ShellLinkHeader {
+impl From<ShellLinkHeader> for [u8; 0x4c] {
     /// Write the data in this header to a `[u8]` for writing to the output file.
-    fn into(self) -> [u8; 0x4c] {
+    fn from(val: ShellLinkHeader) -> Self {
         let mut header_data = [0u8; 0x4c];
         LE::write_u32(&mut header_data[0..], 0x4c);
         LE::write_u128(&mut header_data[4..], CLSID);
-        LE::write_u32(&mut header_data[20..], self.link_flags.bits);
-        LE::write_u32(&mut header_data[24..], self.file_attributes.bits);
-        LE::write_u64(&mut header_data[28..], self.creation_time);
-        LE::write_u64(&mut header_data[36..], self.access_time);
-        LE::write_u64(&mut header_data[44..], self.write_time);
-        LE::write_u32(&mut header_data[52..], self.file_size);
-        LE::write_i32(&mut header_data[56..], self.icon_index);
-        LE::write_u32(&mut header_data[60..], self.show_command as u32);
-        LE::write_u16(&mut header_data[64..], self.hotkey.to_flags_u16());
-        LE::write_u16(&mut header_data[66..], 0);
-        LE::write_u32(&mut header_data[68..], 0);
-        LE::write_u32(&mut header_data[72..], 0);
+        LE::write_u32(&mut header_data[20..], val.link_flags.bits);
+        LE::write_u32(&mut header_data[24..], val.file_attributes.bits);
+        LE::write_u64(&mut header_data[28..], val.creation_time);
+        LE::write_u64(&mut header_data[36..], val.access_time);
+        LE::write_u64(&mut header_data[44..], val.write_time);
+        LE::write_u32(&mut header_data[52..], val.file_size);
+        LE::write_i32(&mut header_data[56..], val.icon_index);
+        let show_command = match val.raw_show_command {
+            Some(raw) if ShowCommand::from(raw) == val.show_command => raw,
+            _ => val.show_command as u32,
+        };
+        LE::write_u32(&mut header_data[60..], show_command);
+        let hotkey = match val.raw_hotkey {
+            Some(raw) if HotkeyFlags::from_flags_u16(raw) == val.hotkey => raw,
+            _ => val.hotkey.to_flags_u16(),
+        };
+        LE::write_u16(&mut header_data[64..], hotkey);
+        header_data[66..].copy_from_slice(&val.reserved);
         header_data
     }
 }
 
+impl TryFrom<&[u8]> for ShellLinkHeader {
+    type Error = MSLinkError;
+
+    /// Read a header from the start of a shell link.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 0x4c {
+            return Err(MSLinkError::truncated("ShellLinkHeader"));
+        }
+        if LE::read_u32(&data[0..]) != 0x4c || LE::read_u128(&data[4..]) != CLSID {
+            return Err(MSLinkError::InvalidHeader);
+        }
+        let show_command = LE::read_u32(&data[60..]);
+        let hotkey = LE::read_u16(&data[64..]);
+        let mut reserved = [0; 10];
+        reserved.copy_from_slice(&data[66..0x4c]);
+        Ok(Self {
+            // undefined bits are kept, so they are written back unchanged
+            link_flags: LinkFlags {
+                bits: LE::read_u32(&data[20..]),
+            },
+            file_attributes: FileAttributeFlags {
+                bits: LE::read_u32(&data[24..]),
+            },
+            creation_time: LE::read_u64(&data[28..]),
+            access_time: LE::read_u64(&data[36..]),
+            write_time: LE::read_u64(&data[44..]),
+            file_size: LE::read_u32(&data[52..]),
+            icon_index: LE::read_i32(&data[56..]),
+            show_command: ShowCommand::from(show_command),
+            hotkey: HotkeyFlags::from_flags_u16(hotkey),
+            raw_show_command: Some(show_command),
+            raw_hotkey: Some(hotkey),
+            reserved,
+        })
+    }
+}
+
 bitflags! {
     /// The LinkFlags structure defines bits that specify which shell linkstructures are present in
     /// the file format after the ShellLinkHeaderstructure (section 2.1).
@@ -334,6 +389,17 @@ impl HotkeyFlags {
         self.low_byte as u16 + ((self.high_byte.bits as u16) << 8)
     }
 
+    /// Read HotkeyFlags from their u16 representation. Unknown keys read as no key, while
+    /// undefined modifier bits are kept.
+    fn from_flags_u16(value: u16) -> Self {
+        Self::new(
+            HotkeyKey::try_from(value as u8).unwrap_or(HotkeyKey::NoKeyAssigned),
+            HotkeyModifiers {
+                bits: (value >> 8) as u8,
+            },
+        )
+    }
+
     /// The primary key assigned to the hotkey
     pub fn key(&self) -> &HotkeyKey {
         &self.low_byte
@@ -425,6 +491,84 @@ pub enum HotkeyKey {
     ScrollLock,
 }
 
+impl TryFrom<u8> for HotkeyKey {
+    type Error = MSLinkError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x00 => Self::NoKeyAssigned,
+            0x30 => Self::Key0,
+            0x31 => Self::Key1,
+            0x32 => Self::Key2,
+            0x33 => Self::Key3,
+            0x34 => Self::Key4,
+            0x35 => Self::Key5,
+            0x36 => Self::Key6,
+            0x37 => Self::Key7,
+            0x38 => Self::Key8,
+            0x39 => Self::Key9,
+            0x41 => Self::KeyA,
+            0x42 => Self::KeyB,
+            0x43 => Self::KeyC,
+            0x44 => Self::KeyD,
+            0x45 => Self::KeyE,
+            0x46 => Self::KeyF,
+            0x47 => Self::KeyG,
+            0x48 => Self::KeyH,
+            0x49 => Self::KeyI,
+            0x4a => Self::KeyJ,
+            0x4b => Self::KeyK,
+            0x4c => Self::KeyL,
+            0x4d => Self::KeyM,
+            0x4e => Self::KeyN,
+            0x4f => Self::KeyO,
+            0x50 => Self::KeyP,
+            0x51 => Self::KeyQ,
+            0x52 => Self::KeyR,
+            0x53 => Self::KeyS,
+            0x54 => Self::KeyT,
+            0x55 => Self::KeyU,
+            0x56 => Self::KeyV,
+            0x57 => Self::KeyW,
+            0x58 => Self::KeyX,
+            0x59 => Self::KeyY,
+            0x5a => Self::KeyZ,
+            0x70 => Self::F1,
+            0x71 => Self::F2,
+            0x72 => Self::F3,
+            0x73 => Self::F4,
+            0x74 => Self::F5,
+            0x75 => Self::F6,
+            0x76 => Self::F7,
+            0x77 => Self::F8,
+            0x78 => Self::F9,
+            0x79 => Self::F10,
+            0x7a => Self::F11,
+            0x7b => Self::F12,
+            0x7c => Self::F13,
+            0x7d => Self::F14,
+            0x7e => Self::F15,
+            0x7f => Self::F16,
+            0x80 => Self::F17,
+            0x81 => Self::F18,
+            0x82 => Self::F19,
+            0x83 => Self::F20,
+            0x84 => Self::F21,
+            0x85 => Self::F22,
+            0x86 => Self::F23,
+            0x87 => Self::F24,
+            0x90 => Self::NumLock,
+            0x91 => Self::ScrollLock,
+            _ => {
+                return Err(MSLinkError::InvalidValue {
+                    field: "hotkey virtual key code".to_owned(),
+                    value: value as u32,
+                })
+            }
+        })
+    }
+}
+
 bitflags! {
     /// An 8-bit unsigned integer that specifies bits that correspond to modifier keys on the
     /// keyboard.
@@ -451,3 +595,15 @@ pub enum ShowCommand {
     /// The application is open, but its window is not shown. It is not given the keyboard focus.
     ShowMinNoActive = 0x07,
 }
+
+impl From<u32> for ShowCommand {
+    /// Any value other than SW_SHOWMAXIMIZED and SW_SHOWMINNOACTIVE is treated as
+    /// SW_SHOWNORMAL.
+    fn from(value: u32) -> Self {
+        match value {
+            0x03 => Self::ShowMaximized,
+            0x07 => Self::ShowMinNoActive,
+            _ => Self::ShowNormal,
+        }
+    }
+}
@@ -1,17 +1,27 @@
-//! # Shell Link writer for Rust.
+//! # Shell Link reader and writer for Rust.
 //! Works on Windows,theoretically support Windows 7,8,10.
 //!
 //! ## Example
 //! A simple example appears as follows:
-//! ```rust
+//! ```no_run
 //! use mslnk::ShellLink;
 //! // ...
 //! let target = r"C:\Users\Admin\Desktop\qq aa\qiuqiu.exe";
 //! let lnk = r"C:\Users\Admin\Desktop\qq.lnk";
 //! let sl = ShellLink::new(target).unwrap();
 //! sl.create_lnk(lnk).unwrap();
-//! ``
+//! ```
+//!
+//! Links can also be serialized to memory or any other [`Write`] sink:
+//! ```no_run
+//! # use mslnk::ShellLink;
+//! let sl = ShellLink::new(r"C:\Windows\notepad.exe").unwrap();
+//! let bytes = sl.to_bytes().unwrap();
+//! sl.write_to(std::io::stdout()).unwrap();
+//! ```
+use byteorder::{ByteOrder, LE};
 use log::debug;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::{prelude::*, BufWriter};
 use std::path::Path;
@@ -23,13 +33,51 @@ pub use header::{
 };
 
 mod linktarget;
-pub use linktarget::LinkTargetIdList;
+pub use linktarget::{ItemID, LinkTargetIdList};
+
+mod shellitem;
+pub use shellitem::{
+    AppItem, CompressedFolderItem, ControlPanelCategoryItem, ControlPanelCplItem, ControlPanelItem,
+    DelegateItem, FileEntryItem, NetworkLocationItem, NetworkLocationType, RootFolder,
+    RootFolderItem, ShellItem, UriItem, VolumeItem,
+};
+
+mod controlpanel;
+pub use controlpanel::ControlPanelCategory;
+
+mod extensionblock;
+pub use extensionblock::{ExtensionBlock, FileEntryExtension, WindowsVersion};
+
+mod filetime;
+
+mod guid;
+pub use guid::Guid;
 
 mod linkinfo;
-pub use linkinfo::LinkInfo;
+pub use linkinfo::{
+    CommonNetworkRelativeLink, CommonNetworkRelativeLinkFlags, DriveType, LinkInfo, LinkInfoFlags,
+    NetworkProviderType, VolumeId,
+};
+
+mod propertystore;
+pub use propertystore::{PropertyStore, PropertyValue};
+
+mod shortname;
+pub use shortname::{is_short_name, short_name};
 
 mod stringdata;
 
+mod builder;
+pub use builder::ShellLinkBuilder;
+
+mod read;
+use linktarget::ItemMetadata;
+use read::Issues;
+pub use read::ReadMode;
+
+mod save;
+pub use save::{SaveOptions, SyncPolicy};
+
 mod extradata;
 pub use extradata::ExtraData;
 
@@ -48,6 +96,11 @@ pub struct ShellLink {
     command_line_arguments: Option<String>,
     icon_location: Option<String>,
     extra_data: Vec<extradata::ExtraData>,
+    /// StringData that didn't decode losslessly, written back while unchanged.
+    raw_strings: Vec<stringdata::RawString>,
+    /// The TerminalBlock and any data after it as read, written back instead of a new
+    /// TerminalBlock.
+    trailer: Option<Vec<u8>>,
 }
 
 impl Default for ShellLink {
@@ -65,55 +118,646 @@ impl Default for ShellLink {
             command_line_arguments: None,
             icon_location: None,
             extra_data: vec![],
+            raw_strings: vec![],
+            trailer: None,
         }
     }
 }
 
 impl ShellLink {
     /// Create a new ShellLink pointing to a location, with otherwise default settings.
+    ///
+    /// The target must be an absolute path starting with a drive letter, or a UNC path such as
+    /// `\\server\share\app.exe`. Drive and share roots (`C:\`) and files directly in a root
+    /// (`C:\app.exe`) are supported, as are trailing separators.
+    ///
+    /// Targets beyond MAX_PATH, with or without the `\\?\` prefix, also get a [`LinkInfo`]
+    /// holding the full Unicode path. If the target is too deep for the 16-bit IDList size, the
+    /// IDList is left out and the link relies on the LinkInfo instead.
     pub fn new<P: AsRef<Path>>(target: P) -> Result<Self, MSLinkError> {
-        use std::fs;
+        let meta = std::fs::metadata(&target)?;
+        let target_path = linktarget::TargetPath::from_path(target.as_ref())?;
+        let metadata = ItemMetadata::read_components(target.as_ref(), target_path.components.len());
+        Self::with_target(
+            &target_path,
+            meta.is_dir(),
+            &ItemMetadata::from_metadata(&meta),
+            &metadata,
+            WindowsVersion::default(),
+        )
+    }
 
-        let meta = fs::metadata(&target)?;
-        let canonical = fs::canonicalize(&target)?.into_boxed_path();
-        let working_dir_path = target
-            .as_ref()
-            .parent()
-            .unwrap()
-            .to_str()
+    /// Create a new ShellLink pointing to a location on a mapped network drive, e.g.
+    /// `Z:\builds\app.exe` with `Z:` mapped to `\\nas\builds`.
+    ///
+    /// Besides the IDList through the drive letter, the link records the share in its
+    /// [`LinkInfo`], so it still resolves for users who mapped the share to another letter.
+    pub fn new_mapped<P: AsRef<Path>>(
+        target: P,
+        net_name: &str,
+        network_provider_type: NetworkProviderType,
+    ) -> Result<Self, MSLinkError> {
+        let meta = std::fs::metadata(&target)?;
+        let target_path = linktarget::TargetPath::from_path(target.as_ref())?;
+        let metadata = ItemMetadata::read_components(target.as_ref(), target_path.components.len());
+        let mut sl = Self::with_target(
+            &target_path,
+            meta.is_dir(),
+            &ItemMetadata::from_metadata(&meta),
+            &metadata,
+            WindowsVersion::default(),
+        )?;
+        sl.set_drive_mapping(&target_path, net_name, network_provider_type)?;
+        Ok(sl)
+    }
+
+    /// Create a new ShellLink pointing to the virtual folder `folder`, such as the Recycle
+    /// Bin, or to `path` beneath it if not empty, see
+    /// [`LinkTargetIdList::set_virtual_target`].
+    ///
+    /// The link has only a LinkTargetIDList, as there is no file system path to store.
+    pub fn new_virtual(folder: RootFolder, path: &str) -> Result<Self, MSLinkError> {
+        let mut sl = Self::default();
+        sl.linktarget_mut()
             .unwrap()
-            .to_owned();
+            .set_virtual_target(folder, path)?;
+        sl.header_mut()
+            .set_link_flags(LinkFlags::IS_UNICODE | LinkFlags::HAS_LINK_TARGET_ID_LIST);
+        if path.is_empty() || path.ends_with(&['\\', '/'][..]) {
+            sl.header_mut()
+                .set_file_attributes(FileAttributeFlags::FILE_ATTRIBUTE_DIRECTORY);
+        }
+        Ok(sl)
+    }
+
+    /// Create a new ShellLink opening the Control Panel applet `applet`, given by its
+    /// canonical name such as `Microsoft.WindowsUpdate` or by its CLSID in registry format.
+    ///
+    /// The link points at the applet in All Control Panel Items.
+    pub fn new_control_panel_applet(applet: &str) -> Result<Self, MSLinkError> {
+        let item = match applet.parse() {
+            Ok(clsid) => ControlPanelItem::new(clsid),
+            Err(_) => ControlPanelItem::from_canonical_name(applet).ok_or_else(|| {
+                MSLinkError::unsupported_path(applet, "is not a known Control Panel item")
+            })?,
+        };
+        Self::new_control_panel(&[
+            ShellItem::ControlPanelCategory(ControlPanelCategoryItem::new(
+                ControlPanelCategory::AllItems,
+            )),
+            ShellItem::ControlPanel(item),
+        ])
+    }
+
+    /// Create a new ShellLink opening the Control Panel category `category`, such as
+    /// Programs.
+    pub fn new_control_panel_category(category: ControlPanelCategory) -> Result<Self, MSLinkError> {
+        Self::new_control_panel(&[ShellItem::ControlPanelCategory(
+            ControlPanelCategoryItem::new(category),
+        )])
+    }
+
+    /// Create a new ShellLink opening the Control Panel applet provided by a CPL file, such as
+    /// the mouse settings in `main.cpl`.
+    pub fn new_control_panel_cpl(item: ControlPanelCplItem) -> Result<Self, MSLinkError> {
+        Self::new_control_panel(&[
+            ShellItem::ControlPanelCategory(ControlPanelCategoryItem::new(
+                ControlPanelCategory::AllItems,
+            )),
+            ShellItem::ControlPanelCpl(item),
+        ])
+    }
+
+    /// Create a link to the Control Panel root folder followed by `items`.
+    fn new_control_panel(items: &[ShellItem]) -> Result<Self, MSLinkError> {
+        let mut sl = Self::new_virtual(RootFolder::ControlPanel, "")?;
+        let list = sl.linktarget_mut().unwrap();
+        for item in items {
+            list.push(item.clone())?;
+        }
+        Ok(sl)
+    }
+
+    /// Create a new ShellLink opening a page of the Settings app, such as
+    /// `ms-settings:windowsupdate`. The `ms-settings:` prefix may be left out.
+    ///
+    /// Settings pages are not shell items, so the link runs Explorer with the page's URI as
+    /// its argument. The path to Explorer is also stored as `%windir%\explorer.exe`, so the
+    /// link works wherever Windows is installed.
+    pub fn new_settings_page(page: &str) -> Result<Self, MSLinkError> {
+        let name = match page.get(..12) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("ms-settings:") => &page[12..],
+            _ => page,
+        };
+        let valid = |c: char| c.is_ascii_alphanumeric() || "-_.?&=".contains(c);
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err(MSLinkError::unsupported_path(
+                page,
+                "is not a settings page",
+            ));
+        }
+        ShellLinkBuilder::new(r"C:\Windows\explorer.exe")
+            .arguments(format!("ms-settings:{}", name))
+            .extra_data(ExtraData::environment_variables(r"%windir%\explorer.exe")?)
+            .build()
+    }
+
+    /// Create a new ShellLink starting the app with the Application User Model ID `aumid`,
+    /// such as `Microsoft.WindowsCalculator_8wekyb3d8bbwe!App`, as `shell:AppsFolder\<AUMID>`
+    /// does. This is how Start menu and desktop shortcuts point at packaged (MSIX) apps.
+    ///
+    /// The link points at the app's item in Applications and, like the links Windows
+    /// creates, also stores the AUMID as System.AppUserModel.ID in a PropertyStoreDataBlock.
+    pub fn new_app(aumid: &str) -> Result<Self, MSLinkError> {
+        if aumid.is_empty() || aumid.contains(&['\\', '/'][..]) {
+            return Err(MSLinkError::unsupported_path(aumid, "is not an AUMID"));
+        }
         let mut sl = Self::default();
+        let list = sl.linktarget_mut().unwrap();
+        list.set_virtual_target(RootFolder::Applications, "")?;
+        let item = AppItem::new(aumid);
+        let properties = ExtraData::from(item.property_store());
+        list.push(ShellItem::App(item))?;
+        sl.header_mut()
+            .set_link_flags(LinkFlags::IS_UNICODE | LinkFlags::HAS_LINK_TARGET_ID_LIST);
+        sl.extra_data_mut().push(properties);
+        Ok(sl)
+    }
 
-        let mut flags = LinkFlags::IS_UNICODE;
+    /// Create a new ShellLink opening the URI `uri`, such as `https://example.com/` or a deep
+    /// link like `ourapp://open?id=1`, with the application registered for its scheme.
+    ///
+    /// Unlike an Internet shortcut (`.url`), the link can have an icon, set with
+    /// [`set_icon_location`](Self::set_icon_location), and a hotkey, set through
+    /// [`header_mut`](Self::header_mut). The item of the URI is stamped with the current
+    /// time, as Explorer does.
+    pub fn new_uri(uri: &str) -> Result<Self, MSLinkError> {
+        let not_a_uri = || MSLinkError::unsupported_path(uri, "is not a URI");
+        let colon = uri.find(':').ok_or_else(not_a_uri)?;
+        let scheme = &uri[..colon];
+        let valid = |c: char| c.is_ascii_alphanumeric() || "+-.".contains(c);
+        // a single letter is a drive, not a scheme
+        if scheme.len() < 2
+            || colon == uri.len() - 1
+            || !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !scheme.chars().all(valid)
+        {
+            return Err(not_a_uri());
+        }
+        let mut item = UriItem::new(uri);
+        item.set_timestamp(filetime::from_system_time(std::time::SystemTime::now()));
+        let mut sl = Self::default();
+        sl.linktarget_mut().unwrap().push(ShellItem::Uri(item))?;
+        sl.header_mut()
+            .set_link_flags(LinkFlags::IS_UNICODE | LinkFlags::HAS_LINK_TARGET_ID_LIST);
+        Ok(sl)
+    }
+
+    /// Start building a link to `target` without touching the file system, see
+    /// [`ShellLinkBuilder`].
+    pub fn builder<P: AsRef<Path>>(target: P) -> ShellLinkBuilder {
+        ShellLinkBuilder::new(target)
+    }
+
+    /// Create a link to `target_path`, with the size and times of the target in the header
+    /// and those of each path component in `metadata` in its item.
+    fn with_target(
+        target_path: &linktarget::TargetPath,
+        is_dir: bool,
+        target: &ItemMetadata,
+        metadata: &[ItemMetadata],
+        windows_version: WindowsVersion,
+    ) -> Result<Self, MSLinkError> {
+        let mut sl = Self::default();
+        let header = sl.header_mut();
+        header.set_creation_time(target.creation_time);
+        header.set_access_time(target.access_time);
+        header.set_write_time(target.write_time);
+
+        let mut flags = LinkFlags::IS_UNICODE | LinkFlags::HAS_LINK_TARGET_ID_LIST;
         sl.header_mut().set_link_flags(flags);
-        if meta.is_dir() {
+        if is_dir {
             sl.header_mut()
                 .set_file_attributes(FileAttributeFlags::FILE_ATTRIBUTE_DIRECTORY);
         } else {
-            flags |= LinkFlags::HAS_WORKING_DIR
-                | LinkFlags::HAS_RELATIVE_PATH
-                | LinkFlags::HAS_LINK_TARGET_ID_LIST;
+            flags |= LinkFlags::HAS_WORKING_DIR | LinkFlags::HAS_RELATIVE_PATH;
             sl.header_mut().set_link_flags(flags);
-            sl.set_relative_path(Some(format!(
-                "./{}",
-                canonical.file_name().unwrap().to_str().unwrap()
-            )));
-            sl.set_working_dir(Some(working_dir_path));
-            sl.header_mut().set_file_size(meta.len() as u32);
-            // set link_target_idlist
-            sl.linktarget_mut().unwrap().set_linktarget(&target);
+            // a file always has at least one component below the drive or share root
+            sl.set_relative_path(Some(format!("./{}", target_path.file_name().unwrap())));
+            sl.set_working_dir(target_path.parent());
+            sl.header_mut().set_file_size(target.file_size as u32);
+        }
+        // set link_target_idlist
+        let path = target_path.path();
+        if let Err(e) = sl.linktarget_mut().unwrap().set_target_path(
+            target_path,
+            is_dir,
+            metadata,
+            windows_version,
+        ) {
+            // the LinkInfo written below holds the full path, so the link still resolves
+            debug!("Omitting the LinkTargetIDList: {}", e);
+            sl.linktarget_id_list = None;
+            sl.header_mut()
+                .update_link_flags(LinkFlags::HAS_LINK_TARGET_ID_LIST, false);
+            if path.encode_utf16().count() < linkinfo::MAX_PATH {
+                sl.set_environment_target(Some(&path))?;
+            }
+        }
+
+        match &target_path.root {
+            // a network target can still be resolved through its share name if the IDList fails
+            linktarget::TargetRoot::Unc { server, share } => {
+                let network_link = CommonNetworkRelativeLink::new(
+                    format!("\\\\{}\\{}", server, share),
+                    Some(NetworkProviderType::Lanman),
+                );
+                sl.set_link_info(Some(LinkInfo::new_network(
+                    network_link,
+                    target_path.suffix(),
+                )));
+            }
+            // readers that can't handle a long IDList fall back to the Unicode local base path
+            linktarget::TargetRoot::Drive(_)
+                if path.encode_utf16().count() >= linkinfo::MAX_PATH
+                    || sl.linktarget_id_list.is_none() =>
+            {
+                sl.set_link_info(Some(LinkInfo::new_local(VolumeId::default(), path)));
+            }
+            linktarget::TargetRoot::Drive(_) => {}
         }
 
         Ok(sl)
     }
 
-    /// Save a shell link.
+    fn set_drive_mapping(
+        &mut self,
+        target_path: &linktarget::TargetPath,
+        net_name: &str,
+        network_provider_type: NetworkProviderType,
+    ) -> Result<(), MSLinkError> {
+        let drive = match target_path.root {
+            linktarget::TargetRoot::Drive(drive) => drive,
+            linktarget::TargetRoot::Unc { .. } => {
+                return Err(MSLinkError::unsupported_path(
+                    &target_path.path(),
+                    "is not on a mapped drive",
+                ))
+            }
+        };
+        let mut network_link = CommonNetworkRelativeLink::new(
+            net_name.trim_end_matches(&['\\', '/'][..]).to_owned(),
+            Some(network_provider_type),
+        );
+        network_link.set_device_name(Some(format!("{}:", drive)));
+        self.set_link_info(Some(LinkInfo::new_network(
+            network_link,
+            target_path.suffix(),
+        )));
+        Ok(())
+    }
+
+    /// Check that the link is consistent and can be written.
+    ///
+    /// Every LinkFlags bit announcing a structure (the LinkTargetIDList, LinkInfo, StringData
+    /// fields and the Darwin, shim, environment and icon environment blocks) must match the
+    /// presence of that structure, StringData must fit its 16-bit character count, the
+    /// LinkTargetIDList sizes must add up, fixed size extra data blocks must have their size
+    /// and FILE_ATTRIBUTE_NORMAL must be the only file attribute if set.
     ///
-    /// Note that this doesn't save any [`ExtraData`](struct.ExtraData.html) entries.
+    /// A default LinkTargetIDList may be kept without HAS_LINK_TARGET_ID_LIST, it is not
+    /// written then.
+    pub fn validate(&self) -> Result<(), MSLinkError> {
+        self.check_writable()?;
+
+        let link_flags = *self.header().link_flags();
+        for (i, block) in self.extra_data.iter().enumerate() {
+            if self.extra_data[..i]
+                .iter()
+                .any(|other| other.signature() == block.signature())
+            {
+                return Err(MSLinkError::InvalidFlags(format!(
+                    "extra data block 0x{:08x} is present more than once",
+                    block.signature()
+                )));
+            }
+        }
+        for (flag, name) in &[
+            (LinkFlags::HAS_DARWIN_ID, "DarwinDataBlock"),
+            (LinkFlags::HAS_EXP_STRING, "EnvironmentVariableDataBlock"),
+            (LinkFlags::HAS_EXP_ICON, "IconEnvironmentDataBlock"),
+            (LinkFlags::RUN_WITH_SHIM_LAYER, "ShimDataBlock"),
+        ] {
+            let present = self
+                .extra_data
+                .iter()
+                .any(|block| block.link_flag() == Some(*flag));
+            check_flag(link_flags, *flag, name, present)?;
+        }
+
+        let file_attributes = *self.header().file_attributes();
+        if file_attributes.contains(FileAttributeFlags::FILE_ATTRIBUTE_NORMAL)
+            && file_attributes != FileAttributeFlags::FILE_ATTRIBUTE_NORMAL
+        {
+            return Err(MSLinkError::InvalidFlags(
+                "FILE_ATTRIBUTE_NORMAL can't be combined with other attributes".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The part of [`validate`](Self::validate) the writer relies on: every structure a flag
+    /// announces is present and has sizes that can be written. Links read from a file may
+    /// contradict themselves otherwise, such as announcing blocks they don't have, and are
+    /// still written back as they were.
+    fn check_writable(&self) -> Result<(), MSLinkError> {
+        let link_flags = *self.header().link_flags();
+        let check_flag = |flag: LinkFlags, name: &str, present: bool| {
+            check_flag(link_flags, flag, name, present)
+        };
+
+        if link_flags.contains(LinkFlags::HAS_LINK_TARGET_ID_LIST) {
+            match &self.linktarget_id_list {
+                Some(list) => list.check_sizes()?,
+                None => check_flag(
+                    LinkFlags::HAS_LINK_TARGET_ID_LIST,
+                    "LinkTargetIDList",
+                    false,
+                )?,
+            }
+        }
+        check_flag(
+            LinkFlags::HAS_LINK_INFO,
+            "LinkInfo",
+            self.link_info.is_some(),
+        )?;
+        for (flag, name, value) in &[
+            (LinkFlags::HAS_NAME, "NAME_STRING", &self.name_string),
+            (
+                LinkFlags::HAS_RELATIVE_PATH,
+                "RELATIVE_PATH",
+                &self.relative_path,
+            ),
+            (LinkFlags::HAS_WORKING_DIR, "WORKING_DIR", &self.working_dir),
+            (
+                LinkFlags::HAS_ARGUMENTS,
+                "COMMAND_LINE_ARGUMENTS",
+                &self.command_line_arguments,
+            ),
+            (
+                LinkFlags::HAS_ICON_LOCATION,
+                "ICON_LOCATION",
+                &self.icon_location,
+            ),
+        ] {
+            check_flag(*flag, name, value.is_some())?;
+            if let Some(s) = value {
+                let count = if link_flags.contains(LinkFlags::IS_UNICODE) {
+                    s.encode_utf16().count()
+                } else {
+                    s.chars().count()
+                };
+                if count > u16::MAX as usize {
+                    return Err(MSLinkError::StringTooLong {
+                        field: name.to_string(),
+                        len: count,
+                    });
+                }
+            }
+        }
+
+        for block in &self.extra_data {
+            block.check_size()?;
+        }
+
+        Ok(())
+    }
+
+    /// Save a shell link, truncating any existing file at `path`. See [`save`](Self::save)
+    /// for atomic and overwrite-aware saving.
     pub fn create_lnk<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), MSLinkError> {
+        // fail before truncating an existing file
+        self.check_writable()?;
         let mut w = BufWriter::new(File::create(path)?);
+        self.write_to(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Save a shell link according to `options`, e.g. atomically through a temporary file,
+    /// without replacing an existing link, or keeping a backup of it.
+    ///
+    /// The link is serialized before the file system is touched, so a link that fails to
+    /// serialize never affects an existing file.
+    pub fn save<P: AsRef<Path>>(&self, path: P, options: &SaveOptions) -> Result<(), MSLinkError> {
+        let data = self.to_bytes()?;
+        save::save(&data, path.as_ref(), options)
+    }
+
+    /// Open the link at `path`, change it with `f` and save it back atomically. Returns
+    /// whether the link changed; a link `f` left as it was isn't rewritten.
+    ///
+    /// Structures `f` doesn't touch are written back byte for byte, including ones this crate
+    /// doesn't understand. If `f` fails, the link is left alone and its error returned.
+    ///
+    /// ```no_run
+    /// use mslnk::ShellLink;
+    ///
+    /// ShellLink::edit(r"C:\Users\Public\Desktop\App.lnk", |sl| {
+    ///     sl.set_arguments(Some("--profile work".to_owned()));
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn edit<P, F>(path: P, f: F) -> Result<bool, MSLinkError>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Self) -> Result<(), MSLinkError>,
+    {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let mut sl = Self::from_bytes(&data)?;
+        f(&mut sl)?;
+        let edited = sl.to_bytes()?;
+        if edited == data {
+            return Ok(false);
+        }
+        save::save(&edited, path, &SaveOptions::default())?;
+        Ok(true)
+    }
+
+    /// Point the link at `target`, an absolute drive or UNC path, keeping its other settings.
+    ///
+    /// The LinkTargetIDList, LinkInfo and EnvironmentVariableDataBlock present in the link are
+    /// rebuilt for the new target, as [`new`](Self::new) would build them. A relative path is
+    /// replaced by `./` and the new file name, a working directory by the target's parent.
+    /// Description, arguments, icon, hotkey and the other extra data blocks, such as console
+    /// and property store blocks, are kept. Blocks describing the old target are removed: the
+    /// tracker and alternate IDList blocks, and the known and special folder blocks pointing
+    /// into the old IDList.
+    ///
+    /// The target is a directory if the link's file attributes say so or `target` ends with a
+    /// separator. The file system isn't touched, so the file size and times in the header are
+    /// kept and also stored in the new target's item. If the new target can't be stored, the
+    /// link is left unchanged.
+    pub fn retarget<P: AsRef<Path>>(&mut self, target: P) -> Result<(), MSLinkError> {
+        let target_path = linktarget::TargetPath::from_path(target.as_ref())?;
+        let is_dir = self
+            .header()
+            .file_attributes()
+            .contains(FileAttributeFlags::FILE_ATTRIBUTE_DIRECTORY)
+            || target
+                .as_ref()
+                .to_string_lossy()
+                .ends_with(&['\\', '/'][..]);
+        if !is_dir && target_path.file_name().is_none() {
+            return Err(MSLinkError::unsupported_path(
+                &target_path.path(),
+                "is a drive or share root and can't be a file",
+            ));
+        }
+        let path = target_path.path();
+
+        let had_id_list = self
+            .header()
+            .link_flags()
+            .contains(LinkFlags::HAS_LINK_TARGET_ID_LIST);
+        let mut id_list = None;
+        if had_id_list {
+            let mut list = LinkTargetIdList::default();
+            // the size and times of the old target are kept for the new one
+            let header = self.header();
+            let target = ItemMetadata {
+                file_size: header.file_size() as u64,
+                creation_time: header.creation_time(),
+                access_time: header.access_time(),
+                write_time: header.write_time(),
+                short_name: None,
+            };
+            let metadata = ItemMetadata::for_target(target_path.components.len(), target);
+            // and so is the Windows version the link was written for
+            let windows_version = self
+                .linktarget()
+                .and_then(LinkTargetIdList::windows_version)
+                .unwrap_or_default();
+            match list.set_target_path(&target_path, is_dir, &metadata, windows_version) {
+                Ok(()) => id_list = Some(list),
+                // the LinkInfo built below holds the full path, so the link still resolves
+                Err(e) => debug!("Omitting the LinkTargetIDList: {}", e),
+            }
+        }
+        let lost_id_list = had_id_list && id_list.is_none();
+
+        let has_environment = self
+            .extra_data
+            .iter()
+            .any(|block| matches!(block, ExtraData::EnvironmentProps(_)));
+        let environment = if has_environment
+            || (lost_id_list && path.encode_utf16().count() < linkinfo::MAX_PATH)
+        {
+            Some(ExtraData::environment_variables(&path)?)
+        } else {
+            None
+        };
+
+        let link_info = match &target_path.root {
+            linktarget::TargetRoot::Unc { server, share } => {
+                let net_name = format!("\\\\{}\\{}", server, share);
+                // keep the provider of a link already on this share
+                let network_provider_type = self
+                    .link_info
+                    .as_ref()
+                    .and_then(LinkInfo::common_network_relative_link)
+                    .filter(|link| link.net_name().eq_ignore_ascii_case(&net_name))
+                    .and_then(|link| link.network_provider_type().copied())
+                    .unwrap_or(NetworkProviderType::Lanman);
+                let network_link =
+                    CommonNetworkRelativeLink::new(net_name, Some(network_provider_type));
+                Some(LinkInfo::new_network(network_link, target_path.suffix()))
+            }
+            linktarget::TargetRoot::Drive(drive) => {
+                let device_name = format!("{}:", drive);
+                let on_drive = |base_path: &str| {
+                    base_path
+                        .get(..2)
+                        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&device_name))
+                };
+                match &self.link_info {
+                    // a mapped drive stays mapped to its share
+                    Some(link_info)
+                        if link_info
+                            .common_network_relative_link()
+                            .is_some_and(|link| on_drive(link.device_name())) =>
+                    {
+                        let network_link = link_info.common_network_relative_link().unwrap();
+                        Some(LinkInfo::new_network(
+                            network_link.clone(),
+                            target_path.suffix(),
+                        ))
+                    }
+                    // the volume is only known for a target on the same drive
+                    Some(link_info) => {
+                        let volume_id = match (link_info.volume_id(), link_info.local_base_path()) {
+                            (Some(volume_id), Some(base_path)) if on_drive(base_path) => {
+                                volume_id.clone()
+                            }
+                            _ => VolumeId::default(),
+                        };
+                        Some(LinkInfo::new_local(volume_id, path))
+                    }
+                    None if path.encode_utf16().count() >= linkinfo::MAX_PATH || lost_id_list => {
+                        Some(LinkInfo::new_local(VolumeId::default(), path))
+                    }
+                    None => None,
+                }
+            }
+        };
+
+        if had_id_list {
+            self.header_mut()
+                .update_link_flags(LinkFlags::HAS_LINK_TARGET_ID_LIST, !lost_id_list);
+            self.linktarget_id_list = id_list;
+        }
+        self.set_link_info(link_info);
+        self.extra_data.retain(|block| {
+            !matches!(
+                block,
+                ExtraData::EnvironmentProps(_)
+                    | ExtraData::KnownFolderProps(_)
+                    | ExtraData::SpecialFolderProps(_)
+                    | ExtraData::TrackerProps(_)
+                    | ExtraData::VistaAndAboveIdListProps(_)
+            )
+        });
+        self.header_mut()
+            .update_link_flags(LinkFlags::HAS_EXP_STRING, environment.is_some());
+        self.extra_data.extend(environment);
+        if self.relative_path.is_some() {
+            let relative_path = target_path.file_name().map(|name| format!("./{}", name));
+            self.set_relative_path(relative_path);
+        }
+        if self.working_dir.is_some() {
+            self.set_working_dir(target_path.parent());
+        }
+        Ok(())
+    }
 
+    /// Serialize the shell link into a byte vector.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MSLinkError> {
+        let mut data = vec![];
+        self.write_to(&mut data)?;
+        Ok(data)
+    }
+
+    /// Write the shell link to any [`Write`] sink, such as an archive entry or
+    /// a network stream. The writer is not buffered or flushed by this method.
+    ///
+    /// Before anything is written, the link is checked for structures its flags announce but
+    /// it doesn't have. Other inconsistencies, which [`validate`](Self::validate) reports, are
+    /// written as they are, so links read from a file round-trip.
+    pub fn write_to<W: Write>(&self, mut w: W) -> Result<(), MSLinkError> {
+        self.check_writable()?;
         debug!("Writing header...");
         let header_data: [u8; 0x4c] = self.shell_link_header.into();
         w.write_all(&header_data)?;
@@ -121,59 +765,270 @@ impl ShellLink {
         let link_flags = *self.header().link_flags();
 
         if link_flags.contains(LinkFlags::HAS_LINK_TARGET_ID_LIST) {
-            let mut data: Vec<u8> = self.linktarget_id_list.clone().unwrap().into();
-            w.write_all(&mut data)?;
+            let data: Vec<u8> = self.linktarget_id_list.clone().unwrap().into();
+            w.write_all(&data)?;
         }
 
         if link_flags.contains(LinkFlags::HAS_LINK_INFO) {
             debug!("LinkInfo is marked as present. Writing.");
-            let mut data: Vec<u8> = self.link_info.clone().unwrap().into();
-            w.write_all(&mut data)?;
+            let data: Vec<u8> = self.link_info.clone().unwrap().into();
+            w.write_all(&data)?;
         }
 
         if link_flags.contains(LinkFlags::HAS_NAME) {
             debug!("Name is marked as present. Writing.");
-            w.write_all(&stringdata::to_data(
+            w.write_all(&stringdata::to_data_as_read(
                 self.name_string.as_ref().unwrap(),
+                "NAME_STRING",
                 link_flags,
-            ))?;
+                &self.raw_strings,
+            )?)?;
         }
 
         if link_flags.contains(LinkFlags::HAS_RELATIVE_PATH) {
             debug!("Relative path is marked as present. Writing.");
-            w.write_all(&stringdata::to_data(
+            w.write_all(&stringdata::to_data_as_read(
                 self.relative_path.as_ref().unwrap(),
+                "RELATIVE_PATH",
                 link_flags,
-            ))?;
+                &self.raw_strings,
+            )?)?;
         }
 
         if link_flags.contains(LinkFlags::HAS_WORKING_DIR) {
             debug!("Working dir is marked as present. Writing.");
-            w.write_all(&stringdata::to_data(
+            w.write_all(&stringdata::to_data_as_read(
                 self.working_dir.as_ref().unwrap(),
+                "WORKING_DIR",
                 link_flags,
-            ))?;
+                &self.raw_strings,
+            )?)?;
         }
 
         if link_flags.contains(LinkFlags::HAS_ARGUMENTS) {
             debug!("Arguments are marked as present. Writing.");
-            w.write_all(&stringdata::to_data(
+            w.write_all(&stringdata::to_data_as_read(
                 self.command_line_arguments.as_ref().unwrap(),
+                "COMMAND_LINE_ARGUMENTS",
                 link_flags,
-            ))?;
+                &self.raw_strings,
+            )?)?;
         }
 
         if link_flags.contains(LinkFlags::HAS_ICON_LOCATION) {
             debug!("Icon Location is marked as present. Writing.");
-            w.write_all(&stringdata::to_data(
+            w.write_all(&stringdata::to_data_as_read(
                 self.icon_location.as_ref().unwrap(),
+                "ICON_LOCATION",
                 link_flags,
-            ))?;
+                &self.raw_strings,
+            )?)?;
+        }
+
+        for block in &self.extra_data {
+            let data: Vec<u8> = block.clone().into();
+            w.write_all(&data)?;
+        }
+        match &self.trailer {
+            Some(trailer) => w.write_all(trailer)?,
+            // TerminalBlock
+            None => w.write_all(&[0u8; 4])?,
         }
 
         Ok(())
     }
 
+    /// Open and parse a shell link.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, MSLinkError> {
+        let data = std::fs::read(path)?;
+        Self::from_bytes(&data)
+    }
+
+    /// Open and parse a shell link in the given [`ReadMode`], see
+    /// [`from_bytes_with`](Self::from_bytes_with).
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        mode: ReadMode,
+    ) -> Result<(Self, Vec<MSLinkError>), MSLinkError> {
+        let data = std::fs::read(path)?;
+        Self::from_bytes_with(&data, mode)
+    }
+
+    /// Parse a shell link from its binary representation.
+    ///
+    /// Damaged structures fail parsing, while a missing TerminalBlock, data following it and
+    /// LinkFlags that don't match the structures present are accepted. See
+    /// [`from_bytes_with`](Self::from_bytes_with) for strict and lenient parsing.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MSLinkError> {
+        Self::parse(data, &mut Issues::new(None))
+    }
+
+    /// Parse a shell link in the given [`ReadMode`], returning the link with the problems
+    /// found in it. Only [`ReadMode::Lenient`] reports warnings, as the strict mode fails on
+    /// the first problem instead.
+    ///
+    /// ```
+    /// use mslnk::{ReadMode, ShellLink};
+    ///
+    /// let mut data = ShellLink::builder(r"C:\app.exe").build().unwrap().to_bytes().unwrap();
+    /// data.extend_from_slice(b"garbage");
+    /// assert!(ShellLink::from_bytes_with(&data, ReadMode::Strict).is_err());
+    ///
+    /// let (sl, warnings) = ShellLink::from_bytes_with(&data, ReadMode::Lenient).unwrap();
+    /// assert_eq!(sl.relative_path().as_deref(), Some("./app.exe"));
+    /// for warning in &warnings {
+    ///     println!("{}", warning);
+    /// }
+    /// assert_eq!(warnings.len(), 1);
+    /// ```
+    pub fn from_bytes_with(
+        data: &[u8],
+        mode: ReadMode,
+    ) -> Result<(Self, Vec<MSLinkError>), MSLinkError> {
+        let mut issues = Issues::new(Some(mode));
+        let sl = Self::parse(data, &mut issues)?;
+        Ok((sl, issues.into_warnings()))
+    }
+
+    fn parse(data: &[u8], issues: &mut Issues) -> Result<Self, MSLinkError> {
+        debug!("Reading header...");
+        let shell_link_header =
+            ShellLinkHeader::try_from(data).map_err(|e| e.within("ShellLinkHeader", 0))?;
+        let link_flags = *shell_link_header.link_flags();
+        let mut sl = Self {
+            shell_link_header,
+            linktarget_id_list: None,
+            ..Default::default()
+        };
+        let mut cursor = 0x4c;
+        // cleared once a structure is damaged beyond its size field, as the following
+        // structures can't be found then
+        let mut intact = true;
+
+        if link_flags.contains(LinkFlags::HAS_LINK_TARGET_ID_LIST) {
+            debug!("A LinkTargetIDList is marked as present. Parsing now.");
+            match LinkTargetIdList::try_from(&data[cursor..]) {
+                Ok(list) => {
+                    cursor += list.size as usize + 2;
+                    sl.linktarget_id_list = Some(list);
+                }
+                Err(e) => {
+                    issues.damaged(e.within("LinkTargetIDList", cursor))?;
+                    let size = data
+                        .get(cursor..)
+                        .filter(|d| d.len() >= 2)
+                        .map(LE::read_u16);
+                    match size.map(|size| cursor + 2 + size as usize) {
+                        Some(end) if end <= data.len() => cursor = end,
+                        _ => intact = false,
+                    }
+                }
+            }
+        }
+
+        if intact && link_flags.contains(LinkFlags::HAS_LINK_INFO) {
+            debug!("LinkInfo is marked as present. Parsing now.");
+            match LinkInfo::try_from(&data[cursor..]) {
+                Ok(info) => {
+                    cursor += info.size as usize;
+                    sl.link_info = Some(info);
+                }
+                Err(e) => {
+                    issues.damaged(e.within("LinkInfo", cursor))?;
+                    let size = data
+                        .get(cursor..)
+                        .filter(|d| d.len() >= 4)
+                        .map(LE::read_u32);
+                    match size.map(|size| cursor + size as usize) {
+                        Some(end) if end > cursor && end <= data.len() => cursor = end,
+                        _ => intact = false,
+                    }
+                }
+            }
+        }
+
+        let mut raw_strings = vec![];
+        let mut read_string =
+            |flag: LinkFlags, name: &str| -> Result<Option<String>, MSLinkError> {
+                if !intact || !link_flags.contains(flag) {
+                    return Ok(None);
+                }
+                match stringdata::parse_string(&data[cursor..], link_flags) {
+                    Ok((len, s)) => {
+                        let string_data = &data[cursor..cursor + len];
+                        raw_strings.extend(stringdata::RawString::lossy(
+                            name,
+                            &s,
+                            link_flags,
+                            string_data,
+                        )?);
+                        cursor += len;
+                        Ok(Some(s))
+                    }
+                    Err(e) => {
+                        issues.damaged(e.within(&format!("StringData/{}", name), cursor))?;
+                        intact = false;
+                        Ok(None)
+                    }
+                }
+            };
+        sl.name_string = read_string(LinkFlags::HAS_NAME, "NAME_STRING")?;
+        sl.relative_path = read_string(LinkFlags::HAS_RELATIVE_PATH, "RELATIVE_PATH")?;
+        sl.working_dir = read_string(LinkFlags::HAS_WORKING_DIR, "WORKING_DIR")?;
+        sl.command_line_arguments =
+            read_string(LinkFlags::HAS_ARGUMENTS, "COMMAND_LINE_ARGUMENTS")?;
+        sl.icon_location = read_string(LinkFlags::HAS_ICON_LOCATION, "ICON_LOCATION")?;
+        sl.raw_strings = raw_strings;
+
+        if intact {
+            debug!("Parsing extra data...");
+            let (extra_data, trailer) = extradata::parse_blocks(&data[cursor..], cursor, issues)?;
+            sl.extra_data = extra_data;
+            sl.trailer = trailer;
+        }
+
+        // keep the link consistent with what could be read
+        let present = [
+            (
+                LinkFlags::HAS_LINK_TARGET_ID_LIST,
+                sl.linktarget_id_list.is_some(),
+            ),
+            (LinkFlags::HAS_LINK_INFO, sl.link_info.is_some()),
+            (LinkFlags::HAS_NAME, sl.name_string.is_some()),
+            (LinkFlags::HAS_RELATIVE_PATH, sl.relative_path.is_some()),
+            (LinkFlags::HAS_WORKING_DIR, sl.working_dir.is_some()),
+            (
+                LinkFlags::HAS_ARGUMENTS,
+                sl.command_line_arguments.is_some(),
+            ),
+            (LinkFlags::HAS_ICON_LOCATION, sl.icon_location.is_some()),
+        ];
+        for (flag, present) in &present {
+            if !present {
+                sl.header_mut().update_link_flags(*flag, false);
+            }
+        }
+        if !intact {
+            sl.header_mut().update_link_flags(
+                LinkFlags::HAS_DARWIN_ID
+                    | LinkFlags::HAS_EXP_STRING
+                    | LinkFlags::HAS_EXP_ICON
+                    | LinkFlags::RUN_WITH_SHIM_LAYER,
+                false,
+            );
+        }
+        if let Err(e) = sl.validate() {
+            issues.violation(e)?;
+        }
+
+        Ok(sl)
+    }
+
+    /// Get the shell link's target, if set
+    pub fn linktarget(&self) -> Option<&LinkTargetIdList> {
+        self.linktarget_id_list.as_ref()
+    }
+
     ///  Get a mutable instance of the shell link's target
     pub fn linktarget_mut(&mut self) -> Option<&mut LinkTargetIdList> {
         self.linktarget_id_list.as_mut()
@@ -188,6 +1043,43 @@ impl ShellLink {
         &mut self.shell_link_header
     }
 
+    /// Get the shell link's LinkInfo, if set
+    pub fn link_info(&self) -> &Option<LinkInfo> {
+        &self.link_info
+    }
+
+    /// Set the shell link's LinkInfo
+    pub fn set_link_info(&mut self, link_info: Option<LinkInfo>) {
+        self.header_mut()
+            .update_link_flags(LinkFlags::HAS_LINK_INFO, link_info.is_some());
+        self.link_info = link_info;
+    }
+
+    /// Get the shell link's extra data blocks
+    pub fn extra_data(&self) -> &Vec<ExtraData> {
+        &self.extra_data
+    }
+
+    /// Get a mutable instance of the shell link's extra data blocks
+    pub fn extra_data_mut(&mut self) -> &mut Vec<ExtraData> {
+        &mut self.extra_data
+    }
+
+    /// Set the target path stored in an EnvironmentVariableDataBlock, which may contain
+    /// environment variables such as `%ProgramFiles%`. This updates the HasExpString flag and
+    /// replaces any previous EnvironmentVariableDataBlock.
+    ///
+    /// Fails if the path does not fit the MAX_PATH sized fields of the block.
+    pub fn set_environment_target(&mut self, target: Option<&str>) -> Result<(), MSLinkError> {
+        let block = target.map(ExtraData::environment_variables).transpose()?;
+        self.extra_data
+            .retain(|block| !matches!(block, ExtraData::EnvironmentProps(_)));
+        self.header_mut()
+            .update_link_flags(LinkFlags::HAS_EXP_STRING, block.is_some());
+        self.extra_data.extend(block);
+        Ok(())
+    }
+
     /// Get the shell link's name, if set
     pub fn name(&self) -> &Option<String> {
         &self.name_string
@@ -249,6 +1141,41 @@ impl ShellLink {
     }
 }
 
+/// Check that `flag` is set in `link_flags` exactly if the structure `name` is `present`.
+fn check_flag(
+    link_flags: LinkFlags,
+    flag: LinkFlags,
+    name: &str,
+    present: bool,
+) -> Result<(), MSLinkError> {
+    if link_flags.contains(flag) == present {
+        return Ok(());
+    }
+    Err(MSLinkError::InvalidFlags(if present {
+        format!("{} is set, but {:?} is not", name, flag)
+    } else {
+        format!("{:?} is set, but there is no {}", flag, name)
+    }))
+}
+
+/// A link to the file `target` of `file_size` bytes, without times.
+#[cfg(test)]
+fn file_link(target: &linktarget::TargetPath, file_size: u64) -> Result<ShellLink, MSLinkError> {
+    let target_metadata = ItemMetadata {
+        file_size,
+        ..ItemMetadata::default()
+    };
+    let metadata = ItemMetadata::for_target(target.components.len(), target_metadata.clone());
+    ShellLink::with_target(
+        target,
+        false,
+        &target_metadata,
+        &metadata,
+        WindowsVersion::default(),
+    )
+}
+
+#[cfg(windows)]
 #[test]
 fn test_create_lnk() {
     let target = r"D:\编程地图书籍、源码\NumPy Essentials.epub";
@@ -256,3 +1183,651 @@ fn test_create_lnk() {
     let sl = ShellLink::new(target).unwrap();
     sl.create_lnk(lnk).unwrap();
 }
+
+#[test]
+fn test_unc_link_info() {
+    let target = linktarget::TargetPath::parse(r"\\fileserver\share\tools\tool.exe").unwrap();
+    let sl = file_link(&target, 0).unwrap();
+    assert!(sl.header().link_flags().contains(LinkFlags::HAS_LINK_INFO));
+    assert_eq!(
+        sl.working_dir().as_deref(),
+        Some(r"\\fileserver\share\tools")
+    );
+    let link_info = sl.link_info().as_ref().unwrap();
+    assert_eq!(link_info.common_path_suffix(), r"tools\tool.exe");
+    assert_eq!(
+        link_info.common_network_relative_link().unwrap().net_name(),
+        r"\\fileserver\share"
+    );
+}
+
+#[test]
+fn test_mapped_drive_link_info() {
+    let target = linktarget::TargetPath::parse(r"z:\builds\app.exe").unwrap();
+    let mut sl = file_link(&target, 0).unwrap();
+    sl.set_drive_mapping(&target, r"\\nas\builds\", NetworkProviderType::Lanman)
+        .unwrap();
+    let link_info = sl.link_info().as_ref().unwrap();
+    assert_eq!(link_info.common_path_suffix(), r"builds\app.exe");
+    let network_link = link_info.common_network_relative_link().unwrap();
+    assert_eq!(network_link.net_name(), r"\\nas\builds");
+    assert_eq!(network_link.device_name(), "Z:");
+    assert!(network_link.flags().contains(
+        CommonNetworkRelativeLinkFlags::VALID_DEVICE
+            | CommonNetworkRelativeLinkFlags::VALID_NET_TYPE
+    ));
+
+    let unc = linktarget::TargetPath::parse(r"\\nas\builds\app.exe").unwrap();
+    assert!(sl
+        .set_drive_mapping(&unc, r"\\nas\builds", NetworkProviderType::Lanman)
+        .is_err());
+}
+
+#[test]
+fn test_read_written_link() {
+    let target = linktarget::TargetPath::parse(r"Z:\builds\app.exe").unwrap();
+    let mut sl = file_link(&target, 1234).unwrap();
+    sl.set_drive_mapping(
+        &target,
+        r"\\nas\builds",
+        NetworkProviderType::Other(0x00ab0000),
+    )
+    .unwrap();
+    sl.set_arguments(Some("--profile 默认".to_owned()));
+    sl.extra_data_mut().push(ExtraData::SpecialFolderProps(vec![
+        0x25, 0, 0, 0, 0x80, 0, 0, 0,
+    ]));
+
+    let data = sl.to_bytes().unwrap();
+
+    let parsed = ShellLink::from_bytes(&data).unwrap();
+    assert_eq!(parsed.header().link_flags(), sl.header().link_flags());
+    assert_eq!(parsed.header().file_size(), 1234);
+    assert_eq!(parsed.arguments(), sl.arguments());
+    assert_eq!(parsed.working_dir(), sl.working_dir());
+    assert_eq!(parsed.relative_path(), sl.relative_path());
+    assert_eq!(parsed.extra_data().len(), 1);
+    let network_link = parsed
+        .link_info()
+        .as_ref()
+        .unwrap()
+        .common_network_relative_link()
+        .unwrap();
+    assert_eq!(
+        network_link.network_provider_type(),
+        Some(&NetworkProviderType::Other(0x00ab0000))
+    );
+
+    let resaved = parsed.to_bytes().unwrap();
+    assert_eq!(resaved, data);
+}
+
+#[test]
+fn test_read_invalid_link() {
+    let error = ShellLink::from_bytes(&[]).unwrap_err();
+    assert_eq!(error.structure_path(), Some("ShellLinkHeader"));
+    assert!(matches!(
+        error.kind(),
+        MSLinkError::Truncated { offset: 0, .. }
+    ));
+    assert!(matches!(
+        ShellLink::from_bytes(&[0u8; 0x4c]).unwrap_err().kind(),
+        MSLinkError::InvalidHeader
+    ));
+
+    let target = linktarget::TargetPath::parse(r"Z:\builds\app.exe").unwrap();
+    let mut sl = file_link(&target, 0).unwrap();
+    sl.set_drive_mapping(&target, r"\\server\builds", NetworkProviderType::Lanman)
+        .unwrap();
+    let data = sl.to_bytes().unwrap();
+    let link_info_offset = 0x4c + sl.linktarget_id_list.unwrap().size as usize + 2;
+    let error = ShellLink::from_bytes(&data[..link_info_offset + 0x20]).unwrap_err();
+    assert_eq!(error.structure_path(), Some("LinkInfo"));
+    assert_eq!(error.offset(), Some(link_info_offset));
+
+    // the drive item follows the IDListSize field and the 20 byte computer item
+    let mut corrupt = data.clone();
+    let drive_item_offset = 0x4c + 2 + 20;
+    corrupt[drive_item_offset] = 0xff;
+    let error = ShellLink::from_bytes(&corrupt).unwrap_err();
+    assert_eq!(error.structure_path(), Some("LinkTargetIDList/ItemID[1]"));
+    assert_eq!(error.offset(), Some(drive_item_offset));
+}
+
+#[test]
+fn test_long_path_target() {
+    let long_dir = "d".repeat(250);
+    let target = linktarget::TargetPath::parse(&format!(r"\\?\C:\{}\app.exe", long_dir)).unwrap();
+    let sl = file_link(&target, 0).unwrap();
+    assert!(sl
+        .header()
+        .link_flags()
+        .contains(LinkFlags::HAS_LINK_TARGET_ID_LIST | LinkFlags::HAS_LINK_INFO));
+    let link_info = sl.link_info().as_ref().unwrap();
+    let path = format!(r"C:\{}\app.exe", long_dir);
+    assert_eq!(link_info.local_base_path_unicode(), Some(path.as_str()));
+    assert_eq!(sl.working_dir().as_deref(), Some(&path[..path.len() - 8]));
+
+    // short targets don't get a LinkInfo, even when their UTF-8 form is beyond MAX_PATH
+    let target = linktarget::TargetPath::parse(r"C:\app.exe").unwrap();
+    let sl = file_link(&target, 0).unwrap();
+    assert!(sl.link_info().is_none());
+    let target = format!(r"C:\{}\app.exe", "文".repeat(100));
+    let sl = file_link(&linktarget::TargetPath::parse(&target).unwrap(), 0).unwrap();
+    assert!(sl.link_info().is_none());
+    let mut sl = file_link(&linktarget::TargetPath::parse(r"C:\app.exe").unwrap(), 0).unwrap();
+    sl.retarget(&target).unwrap();
+    assert!(sl.link_info().is_none());
+}
+
+#[test]
+fn test_target_too_long_for_idlist() {
+    let component = "a".repeat(200);
+    let path = format!(r"C:\{}", vec![component.as_str(); 200].join("\\"));
+    let target = linktarget::TargetPath::parse(&path).unwrap();
+    let sl = file_link(&target, 0).unwrap();
+    assert!(!sl
+        .header()
+        .link_flags()
+        .contains(LinkFlags::HAS_LINK_TARGET_ID_LIST));
+    assert!(!sl.header().link_flags().contains(LinkFlags::HAS_EXP_STRING));
+    assert_eq!(
+        sl.link_info().as_ref().unwrap().local_base_path_unicode(),
+        Some(path.as_str())
+    );
+}
+
+#[test]
+fn test_environment_target() {
+    let mut sl = ShellLink::default();
+    sl.set_environment_target(Some(r"%ProgramFiles%\app\app.exe"))
+        .unwrap();
+    assert!(sl.header().link_flags().contains(LinkFlags::HAS_EXP_STRING));
+    assert!(sl.set_environment_target(Some(&"a".repeat(300))).is_err());
+    assert_eq!(sl.extra_data().len(), 1);
+    sl.set_environment_target(None).unwrap();
+    assert!(!sl.header().link_flags().contains(LinkFlags::HAS_EXP_STRING));
+    assert!(sl.extra_data().is_empty());
+}
+
+#[test]
+fn test_write_to_matches_create_lnk() {
+    let target = linktarget::TargetPath::parse(r"C:\Tools\app.exe").unwrap();
+    let mut sl = file_link(&target, 42).unwrap();
+    sl.set_arguments(Some("--profile default".to_owned()));
+
+    let path = std::env::temp_dir().join("mslnk_test_write_to_matches_create_lnk.lnk");
+    sl.create_lnk(&path).unwrap();
+    let saved = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut written = std::io::Cursor::new(vec![]);
+    sl.write_to(&mut written).unwrap();
+    assert_eq!(written.into_inner(), saved);
+    assert_eq!(sl.to_bytes().unwrap(), saved);
+}
+
+#[test]
+fn test_save_parses_back() {
+    let target = linktarget::TargetPath::parse(r"C:\Tools\app.exe").unwrap();
+    let sl = file_link(&target, 42).unwrap();
+    let path = std::env::temp_dir().join(format!("mslnk_test_save_{}.lnk", std::process::id()));
+    sl.save(&path, &SaveOptions::default()).unwrap();
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(data, sl.to_bytes().unwrap());
+}
+
+#[test]
+fn test_validate() {
+    let target = linktarget::TargetPath::parse(r"C:\Tools\app.exe").unwrap();
+    let sl = file_link(&target, 42).unwrap();
+    sl.validate().unwrap();
+    ShellLink::default().validate().unwrap();
+
+    // a flag without its structure used to panic in write_to
+    let mut broken = sl.clone();
+    broken
+        .header_mut()
+        .update_link_flags(LinkFlags::HAS_NAME, true);
+    assert!(broken.validate().is_err());
+    assert!(broken.to_bytes().is_err());
+
+    let mut broken = sl.clone();
+    broken
+        .header_mut()
+        .update_link_flags(LinkFlags::HAS_WORKING_DIR, false);
+    assert!(broken.validate().is_err());
+
+    // a block flag without its block is written as it is
+    let mut broken = sl.clone();
+    broken
+        .header_mut()
+        .update_link_flags(LinkFlags::HAS_DARWIN_ID, true);
+    assert!(broken.validate().is_err());
+    broken.to_bytes().unwrap();
+
+    let mut broken = sl.clone();
+    broken.set_environment_target(Some("%TOOLS%")).unwrap();
+    broken
+        .header_mut()
+        .update_link_flags(LinkFlags::HAS_EXP_STRING, false);
+    assert!(broken.validate().is_err());
+
+    let mut broken = sl.clone();
+    broken.set_arguments(Some("a".repeat(70000)));
+    assert!(broken.validate().is_err());
+
+    let mut broken = sl.clone();
+    broken.linktarget_mut().unwrap().size += 1;
+    assert!(broken.validate().is_err());
+
+    let mut broken = sl.clone();
+    broken
+        .extra_data_mut()
+        .push(ExtraData::DarwinProps(vec![0; 4]));
+    broken
+        .header_mut()
+        .update_link_flags(LinkFlags::HAS_DARWIN_ID, true);
+    assert!(broken.validate().is_err());
+
+    let mut broken = sl;
+    broken.header_mut().set_file_attributes(
+        FileAttributeFlags::FILE_ATTRIBUTE_NORMAL | FileAttributeFlags::FILE_ATTRIBUTE_READONLY,
+    );
+    assert!(broken.validate().is_err());
+}
+
+#[test]
+fn test_read_modes() {
+    let sl = ShellLinkBuilder::new(r"C:\Tools\app.exe")
+        .description("App")
+        .arguments("--verbose")
+        .extra_data(ExtraData::environment_variables(r"%TOOLS%\app.exe").unwrap())
+        .build()
+        .unwrap();
+    let data = sl.to_bytes().unwrap();
+    let (_, warnings) = ShellLink::from_bytes_with(&data, ReadMode::Strict).unwrap();
+    assert!(warnings.is_empty());
+
+    // trailing garbage is only a violation
+    let mut garbage = data.clone();
+    garbage.extend_from_slice(&[0xcc; 3]);
+    ShellLink::from_bytes(&garbage).unwrap();
+    assert!(ShellLink::from_bytes_with(&garbage, ReadMode::Strict).is_err());
+    let (_, warnings) = ShellLink::from_bytes_with(&garbage, ReadMode::Lenient).unwrap();
+    assert!(matches!(
+        warnings[0].kind(),
+        MSLinkError::TrailingData { len: 3 }
+    ));
+
+    // an unknown block is kept, but violates the specification
+    let block_offset = data.len() - 4 - 0x314;
+    let mut unknown_block = data.clone();
+    LE::write_u32(&mut unknown_block[block_offset + 4..], 0xa00000ff);
+    let parsed = ShellLink::from_bytes(&unknown_block).unwrap();
+    assert_eq!(parsed.extra_data()[0].signature(), 0xa00000ff);
+    assert!(ShellLink::from_bytes_with(&unknown_block, ReadMode::Strict).is_err());
+    let (_, warnings) = ShellLink::from_bytes_with(&unknown_block, ReadMode::Lenient).unwrap();
+    let paths: Vec<_> = warnings.iter().map(|w| w.structure_path()).collect();
+    // the second warning is about HAS_EXP_STRING
+    assert_eq!(paths, [Some("ExtraData/Block[0]"), None]);
+
+    // a block too short for its signature is skipped, as its size is intact
+    let mut short_block = data[..block_offset].to_vec();
+    short_block.extend_from_slice(&[6, 0, 0, 0, 0xaa, 0xbb]);
+    short_block.extend_from_slice(&data[block_offset..]);
+    assert!(ShellLink::from_bytes(&short_block).is_err());
+    let (parsed, warnings) = ShellLink::from_bytes_with(&short_block, ReadMode::Lenient).unwrap();
+    assert_eq!(parsed.extra_data().len(), 1);
+    assert_eq!(parsed.arguments().as_deref(), Some("--verbose"));
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].structure_path(), Some("ExtraData/Block[0]"));
+
+    // a damaged item is skipped with the whole IDList
+    let mut damaged_item = data.clone();
+    damaged_item[0x4c + 2 + 20] = 0xff;
+    let (parsed, warnings) = ShellLink::from_bytes_with(&damaged_item, ReadMode::Lenient).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(!parsed
+        .header()
+        .link_flags()
+        .contains(LinkFlags::HAS_LINK_TARGET_ID_LIST));
+    assert_eq!(parsed.name().as_deref(), Some("App"));
+    assert_eq!(parsed.extra_data().len(), 1);
+
+    // a cut StringData loses everything behind it
+    let arguments_offset = data.len() - 4 - 0x314 - (2 + 9 * 2);
+    let truncated = &data[..arguments_offset + 6];
+    let (parsed, warnings) = ShellLink::from_bytes_with(truncated, ReadMode::Lenient).unwrap();
+    assert_eq!(
+        warnings[0].structure_path(),
+        Some("StringData/COMMAND_LINE_ARGUMENTS")
+    );
+    assert_eq!(warnings[0].offset(), Some(arguments_offset));
+    assert_eq!(parsed.working_dir().as_deref(), Some(r"C:\Tools"));
+    assert_eq!(parsed.arguments(), &None);
+    // the partial link is consistent and can be written again
+    parsed.to_bytes().unwrap();
+}
+
+#[test]
+fn test_lone_surrogate_round_trip() {
+    let sl = ShellLinkBuilder::new(r"C:\Tools\app.exe")
+        .arguments("a\u{fffd}b")
+        .build()
+        .unwrap();
+    let mut data = sl.to_bytes().unwrap();
+    let at = data.windows(2).position(|w| w == [0xfd, 0xff]).unwrap();
+    data[at..at + 2].copy_from_slice(&[0x00, 0xd8]);
+
+    let mut sl = ShellLink::from_bytes(&data).unwrap();
+    assert_eq!(sl.arguments().as_deref(), Some("a\u{fffd}b"));
+    assert_eq!(sl.to_bytes().unwrap(), data);
+    // an edited string is encoded from its new value
+    sl.set_arguments(Some("ab".to_owned()));
+    assert_eq!(sl.to_bytes().unwrap().len(), data.len() - 2);
+}
+
+#[test]
+fn test_round_trip_fixtures() {
+    let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src/example/fixtures"));
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let data = std::fs::read(&path).unwrap();
+        let sl = ShellLink::from_bytes(&data).unwrap();
+        assert_eq!(sl.to_bytes().unwrap(), data, "{}", path.display());
+        count += 1;
+    }
+    assert_ne!(count, 0);
+
+    // a link written by Windows follows the specification
+    let data = std::fs::read(dir.join("win10_file.lnk")).unwrap();
+    let (_, warnings) = ShellLink::from_bytes_with(&data, ReadMode::Strict).unwrap();
+    assert!(warnings.is_empty());
+    // one announcing a block it doesn't have is only reported
+    let exp_icon = std::fs::read(dir.join("win10_file_exp_icon.lnk")).unwrap();
+    let (_, warnings) = ShellLink::from_bytes_with(&exp_icon, ReadMode::Lenient).unwrap();
+    assert!(matches!(warnings[0].kind(), MSLinkError::InvalidFlags(_)));
+
+    // editing a string leaves the structures around it untouched
+    let mut sl = ShellLink::from_bytes(&data).unwrap();
+    sl.set_arguments(Some("--new".to_owned()));
+    let edited = sl.to_bytes().unwrap();
+    let strings_offset = 0x4c + 2 + 0xbd + 0x3c;
+    let added = 2 + 5 * 2;
+    assert_eq!(edited.len(), data.len() + added);
+    assert_eq!(edited[..20], data[..20]);
+    assert_eq!(edited[24..strings_offset], data[24..strings_offset]);
+    assert_eq!(edited[edited.len() - 0x64..], data[data.len() - 0x64..]);
+}
+
+#[test]
+fn test_edit() {
+    let fixture = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/example/fixtures/win10_file.lnk"
+    );
+    let data = std::fs::read(fixture).unwrap();
+    let path = std::env::temp_dir().join(format!("mslnk_test_edit_{}.lnk", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+
+    let changed = ShellLink::edit(&path, |sl| {
+        sl.set_arguments(Some("--profile work".to_owned()));
+        *sl.header_mut().hotkey_mut() =
+            HotkeyFlags::new(HotkeyKey::KeyP, HotkeyModifiers::HOTKEYF_CONTROL);
+        Ok(())
+    })
+    .unwrap();
+    assert!(changed);
+    let edited = ShellLink::open(&path).unwrap();
+    assert_eq!(edited.arguments().as_deref(), Some("--profile work"));
+    assert_eq!(edited.header().hotkey().key(), &HotkeyKey::KeyP);
+    // the TrackerDataBlock and TerminalBlock are untouched
+    let saved = std::fs::read(&path).unwrap();
+    assert_eq!(saved[saved.len() - 0x64..], data[data.len() - 0x64..]);
+
+    // setting the same value again leaves the file alone
+    let changed = ShellLink::edit(&path, |sl| {
+        sl.set_arguments(Some("--profile work".to_owned()));
+        Ok(())
+    })
+    .unwrap();
+    assert!(!changed);
+
+    // a failing edit doesn't save
+    let result = ShellLink::edit(&path, |sl| {
+        sl.set_arguments(None);
+        sl.set_environment_target(Some(&"x".repeat(300)))
+    });
+    assert!(result.is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), saved);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_edit_inconsistent_link() {
+    // HAS_EXP_ICON is set, but there is no IconEnvironmentDataBlock
+    let fixture = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/example/fixtures/win10_file_exp_icon.lnk"
+    );
+    let data = std::fs::read(fixture).unwrap();
+    let path = std::env::temp_dir().join(format!(
+        "mslnk_test_edit_inconsistent_{}.lnk",
+        std::process::id()
+    ));
+    std::fs::write(&path, &data).unwrap();
+
+    let changed = ShellLink::edit(&path, |sl| {
+        sl.set_arguments(Some("--profile work".to_owned()));
+        Ok(())
+    })
+    .unwrap();
+    assert!(changed);
+    let saved = std::fs::read(&path).unwrap();
+    let edited = ShellLink::from_bytes(&saved).unwrap();
+    assert_eq!(edited.arguments().as_deref(), Some("--profile work"));
+    assert!(edited
+        .header()
+        .link_flags()
+        .contains(LinkFlags::HAS_EXP_ICON));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_retarget() {
+    let console = ExtraData::ConsoleFeProps(vec![0xe9, 0xfd, 0, 0]);
+    let link_to = |target: &str| {
+        ShellLinkBuilder::new(target)
+            .description("App")
+            .arguments("--profile default")
+            .icon(r"C:\Icons\app.ico", 3)
+            .hotkey(HotkeyFlags::new(
+                HotkeyKey::KeyA,
+                HotkeyModifiers::HOTKEYF_CONTROL,
+            ))
+            .extra_data(console.clone())
+            .extra_data(ExtraData::environment_variables(target).unwrap())
+            .build()
+            .unwrap()
+    };
+
+    // the result is the link that would have been built for the new target
+    let mut sl = link_to(r"D:\Tools\app.exe");
+    sl.extra_data_mut()
+        .push(ExtraData::TrackerProps(vec![0; 0x58]));
+    sl.retarget(r"E:\Apps\app.exe").unwrap();
+    assert_eq!(
+        sl.to_bytes().unwrap(),
+        link_to(r"E:\Apps\app.exe").to_bytes().unwrap()
+    );
+
+    // the volume is kept on the same drive, a mapped drive stays mapped
+    let mut sl = ShellLinkBuilder::new(r"Z:\builds\app.exe")
+        .drive_mapping(r"\\nas\builds", NetworkProviderType::Lanman)
+        .build()
+        .unwrap();
+    sl.retarget(r"Z:\nightly\app.exe").unwrap();
+    let link_info = sl.link_info().as_ref().unwrap();
+    assert_eq!(link_info.common_path_suffix(), r"nightly\app.exe");
+    assert_eq!(
+        link_info
+            .common_network_relative_link()
+            .unwrap()
+            .device_name(),
+        "Z:"
+    );
+    sl.retarget(r"\\nas\other\app.exe").unwrap();
+    let link_info = sl.link_info().as_ref().unwrap();
+    assert_eq!(
+        link_info.common_network_relative_link().unwrap().net_name(),
+        r"\\nas\other"
+    );
+    assert_eq!(sl.working_dir().as_deref(), Some(r"\\nas\other\"));
+
+    // an invalid target leaves the link alone
+    let before = sl.to_bytes().unwrap();
+    assert!(sl.retarget("app.exe").is_err());
+    assert!(sl.retarget("D:").is_err());
+    assert_eq!(sl.to_bytes().unwrap(), before);
+}
+
+#[test]
+fn test_new_virtual() {
+    let sl = ShellLink::new_virtual(RootFolder::RecycleBin, "").unwrap();
+    sl.validate().unwrap();
+    assert!(sl
+        .header()
+        .file_attributes()
+        .contains(FileAttributeFlags::FILE_ATTRIBUTE_DIRECTORY));
+    let parsed = ShellLink::from_bytes_with(&sl.to_bytes().unwrap(), ReadMode::Strict).unwrap();
+    assert!(parsed.1.is_empty());
+    assert_eq!(parsed.0.linktarget().unwrap().to_string(), "Recycle Bin");
+    assert!(parsed.0.link_info().is_none());
+    assert_eq!(parsed.0.relative_path(), &None);
+
+    let sl = ShellLink::new_virtual(RootFolder::Libraries, r"Music\song.mp3").unwrap();
+    assert_eq!(
+        sl.header().file_attributes(),
+        &FileAttributeFlags::FILE_ATTRIBUTE_NORMAL
+    );
+    assert_eq!(
+        sl.linktarget().unwrap().to_string(),
+        "Libraries > Music > song.mp3"
+    );
+}
+
+#[test]
+fn test_control_panel_links() {
+    let sl = ShellLink::new_control_panel_applet("Microsoft.WindowsUpdate").unwrap();
+    let parsed = ShellLink::from_bytes(&sl.to_bytes().unwrap()).unwrap();
+    assert_eq!(
+        parsed.linktarget().unwrap().to_string(),
+        "Control Panel > All Control Panel Items > Microsoft.WindowsUpdate"
+    );
+    let sl = ShellLink::new_control_panel_applet("{36EEF7DB-88AD-4E81-AD49-0E313F0C35F8}").unwrap();
+    assert_eq!(
+        sl.linktarget().unwrap().items().last().unwrap().to_string(),
+        "Microsoft.WindowsUpdate"
+    );
+    assert!(ShellLink::new_control_panel_applet("Microsoft.Unknown").is_err());
+
+    let sl = ShellLink::new_control_panel_category(ControlPanelCategory::Programs).unwrap();
+    assert_eq!(
+        sl.linktarget().unwrap().to_string(),
+        "Control Panel > Programs"
+    );
+
+    let cpl = ControlPanelCplItem::new(
+        r"C:\Windows\System32\main.cpl",
+        "Mouse",
+        "Customize your mouse settings",
+        -100,
+    );
+    let sl = ShellLink::new_control_panel_cpl(cpl).unwrap();
+    let parsed = ShellLink::from_bytes(&sl.to_bytes().unwrap()).unwrap();
+    match parsed.linktarget().unwrap().items().last().unwrap() {
+        ShellItem::ControlPanelCpl(item) => {
+            assert_eq!(item.path(), r"C:\Windows\System32\main.cpl");
+            assert_eq!(item.name(), "Mouse");
+            assert_eq!(item.comments(), "Customize your mouse settings");
+            assert_eq!(item.icon_index(), -100);
+        }
+        item => panic!("not a CPL item: {:?}", item),
+    }
+}
+
+#[test]
+fn test_settings_page_link() {
+    let sl = ShellLink::new_settings_page("ms-settings:windowsupdate").unwrap();
+    assert_eq!(sl.arguments().as_deref(), Some("ms-settings:windowsupdate"));
+    assert!(sl.header().link_flags().contains(LinkFlags::HAS_EXP_STRING));
+    let sl = ShellLink::new_settings_page("display").unwrap();
+    assert_eq!(sl.arguments().as_deref(), Some("ms-settings:display"));
+    assert!(ShellLink::new_settings_page("ms-settings:").is_err());
+    assert!(ShellLink::new_settings_page("display\" /evil").is_err());
+}
+
+#[test]
+fn test_app_link() {
+    let aumid = "Microsoft.WindowsCalculator_8wekyb3d8bbwe!App";
+    let sl = ShellLink::new_app(aumid).unwrap();
+    let parsed = ShellLink::from_bytes_with(&sl.to_bytes().unwrap(), ReadMode::Strict).unwrap();
+    assert!(parsed.1.is_empty());
+    let sl = parsed.0;
+    assert_eq!(
+        sl.linktarget().unwrap().to_string(),
+        format!("Applications > {}", aumid)
+    );
+    assert!(!sl
+        .header()
+        .file_attributes()
+        .contains(FileAttributeFlags::FILE_ATTRIBUTE_DIRECTORY));
+    let store = match &sl.extra_data()[..] {
+        [block @ ExtraData::PropertyStoreProps(_)] => PropertyStore::try_from(block.data()),
+        blocks => panic!("unexpected extra data: {:?}", blocks),
+    };
+    assert_eq!(store.unwrap().app_user_model_id(), Some(aumid));
+    assert!(ShellLink::new_app("").is_err());
+    assert!(ShellLink::new_app(r"C:\Windows\notepad.exe").is_err());
+}
+
+#[test]
+fn test_uri_link() {
+    let mut sl = ShellLink::new_uri("ourapp://open?id=1").unwrap();
+    sl.set_icon_location(Some(r"C:\Program Files\OurApp\ourapp.exe".to_owned()));
+    sl.header_mut().hotkey_mut().set_key(HotkeyKey::KeyO);
+    sl.header_mut()
+        .hotkey_mut()
+        .set_modifiers(HotkeyModifiers::HOTKEYF_CONTROL | HotkeyModifiers::HOTKEYF_ALT);
+    let parsed = ShellLink::from_bytes_with(&sl.to_bytes().unwrap(), ReadMode::Strict).unwrap();
+    assert!(parsed.1.is_empty());
+    let sl = parsed.0;
+    assert_eq!(
+        sl.icon_location().as_deref(),
+        Some(r"C:\Program Files\OurApp\ourapp.exe")
+    );
+    assert_eq!(sl.header().hotkey().key(), &HotkeyKey::KeyO);
+    match &sl.linktarget().unwrap().items().collect::<Vec<_>>()[..] {
+        [ShellItem::Uri(item)] => {
+            assert_eq!(item.uri(), "ourapp://open?id=1");
+            assert!(item.timestamp().unwrap() > 0);
+        }
+        items => panic!("not a URI item: {:?}", items),
+    }
+
+    assert!(ShellLink::new_uri("https://example.com/").is_ok());
+    for uri in &[
+        r"C:\Windows",
+        "https:",
+        "1http://x",
+        "no scheme",
+        "a b://x",
+        "example",
+        "https",
+    ] {
+        assert!(ShellLink::new_uri(uri).is_err(), "{}", uri);
+    }
+}
@@ -1,4 +1,18 @@
+use crate::{stringdata, MSLinkError};
 use bitflags::bitflags;
+use byteorder::{ByteOrder, LE};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The longest path Windows handles without the `\\?\` long path prefix, including the
+/// terminating NULL.
+pub(crate) const MAX_PATH: usize = 260;
+
+/// Whether a path also needs a Unicode copy, because the system default code page cannot
+/// represent it or because readers limit the ANSI copy to MAX_PATH.
+fn needs_unicode(path: &str) -> bool {
+    !path.is_ascii() || path.len() >= MAX_PATH
+}
 
 /// The LinkInfo structure specifies information necessary to resolve a
 /// linktarget if it is not found in its original location. This includes
@@ -14,6 +28,11 @@ pub struct LinkInfo {
     /// LocalBasePathUnicode, and CommonNetworkRelativeLinkfields are present
     /// in this structure.
     link_info_flags: LinkInfoFlags,
+    /// An optional VolumeID structure (section 2.3.1) that specifies
+    /// information about the volume that the link target was on when the link
+    /// was created. This field is present if the VolumeIDAndLocalBasePath flag
+    /// is set.
+    volume_id: Option<VolumeId>,
     /// An optional, NULL–terminated string, defined by the system default code
     /// page, which is used to construct the full path to the link item or link
     /// target by appending the string in the CommonPathSuffix field. This
@@ -39,6 +58,9 @@ pub struct LinkInfo {
     /// only if the value of the LinkInfoHeaderSize field is greater than or
     /// equal to 0x00000024.
     common_path_suffix_unicode: Option<String>,
+    /// The structure as read. A LinkInfo can't be changed once created, so a parsed one is
+    /// written back as is, keeping the layout and padding chosen by its writer.
+    raw: Option<Vec<u8>>,
 }
 
 impl Default for LinkInfo {
@@ -46,23 +68,432 @@ impl Default for LinkInfo {
         Self {
             size: 0,
             link_info_flags: LinkInfoFlags::empty(),
-
+            volume_id: None,
             local_base_path: None,
             common_network_relative_link: None,
             common_path_suffix: String::new(),
             local_base_path_unicode: None,
             common_path_suffix_unicode: None,
+            raw: None,
+        }
+    }
+}
+
+impl LinkInfo {
+    /// Create the LinkInfo of a target on a network share, where `common_path_suffix` is the
+    /// path of the target below the share, e.g. `tools\app.exe`.
+    pub fn new_network(
+        common_network_relative_link: CommonNetworkRelativeLink,
+        common_path_suffix: String,
+    ) -> Self {
+        let common_path_suffix_unicode = if needs_unicode(&common_path_suffix) {
+            Some(common_path_suffix.clone())
+        } else {
+            None
+        };
+        Self {
+            size: 0,
+            link_info_flags: LinkInfoFlags::COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX,
+            volume_id: None,
+            local_base_path: None,
+            common_network_relative_link: Some(common_network_relative_link),
+            common_path_suffix,
+            local_base_path_unicode: None,
+            common_path_suffix_unicode,
+            raw: None,
+        }
+    }
+
+    /// Create the LinkInfo of a target on a local volume, where `local_base_path` is the full
+    /// path of the target, e.g. `C:\tools\app.exe`. Paths that are not ASCII or reach
+    /// MAX_PATH are also stored in the Unicode fields.
+    pub fn new_local(volume_id: VolumeId, local_base_path: String) -> Self {
+        let local_base_path_unicode = if needs_unicode(&local_base_path) {
+            Some(local_base_path.clone())
+        } else {
+            None
+        };
+        Self {
+            size: 0,
+            link_info_flags: LinkInfoFlags::VOLUME_ID_AND_LOCAL_BASE_PATH,
+            volume_id: Some(volume_id),
+            local_base_path: Some(local_base_path),
+            common_network_relative_link: None,
+            common_path_suffix: String::new(),
+            local_base_path_unicode,
+            common_path_suffix_unicode: None,
+            raw: None,
+        }
+    }
+
+    /// Flags that specify whether the VolumeID, LocalBasePath, LocalBasePathUnicode, and
+    /// CommonNetworkRelativeLink fields are present in this structure.
+    pub fn link_info_flags(&self) -> &LinkInfoFlags {
+        &self.link_info_flags
+    }
+
+    /// The volume the link target was on, if the VolumeIDAndLocalBasePath flag is set.
+    pub fn volume_id(&self) -> Option<&VolumeId> {
+        self.volume_id.as_ref()
+    }
+
+    /// The local base path, if the VolumeIDAndLocalBasePath flag is set.
+    pub fn local_base_path(&self) -> Option<&str> {
+        self.local_base_path.as_deref()
+    }
+
+    /// Information about the network location where the link target is stored.
+    pub fn common_network_relative_link(&self) -> Option<&CommonNetworkRelativeLink> {
+        self.common_network_relative_link.as_ref()
+    }
+
+    /// The path appended to the local base path or net name to form the full target path.
+    pub fn common_path_suffix(&self) -> &str {
+        &self.common_path_suffix
+    }
+
+    /// The Unicode version of the local base path, if present.
+    pub fn local_base_path_unicode(&self) -> Option<&str> {
+        self.local_base_path_unicode.as_deref()
+    }
+
+    /// The Unicode version of the common path suffix, if present.
+    pub fn common_path_suffix_unicode(&self) -> Option<&str> {
+        self.common_path_suffix_unicode.as_deref()
+    }
+}
+
+impl From<LinkInfo> for Vec<u8> {
+    fn from(val: LinkInfo) -> Self {
+        if let Some(raw) = val.raw {
+            return raw;
+        }
+        // the Unicode offsets are only present with the larger header
+        let has_unicode =
+            val.local_base_path_unicode.is_some() || val.common_path_suffix_unicode.is_some();
+        let header_size = if has_unicode { 0x24 } else { 0x1c };
+        let mut data = vec![0u8; header_size];
+        LE::write_u32(&mut data[4..8], header_size as u32);
+        LE::write_u32(&mut data[8..12], val.link_info_flags.bits);
+
+        if val
+            .link_info_flags
+            .contains(LinkInfoFlags::VOLUME_ID_AND_LOCAL_BASE_PATH)
+        {
+            let offset = data.len() as u32;
+            LE::write_u32(&mut data[12..16], offset);
+            data.append(&mut val.volume_id.unwrap_or_default().into());
+            let offset = data.len() as u32;
+            LE::write_u32(&mut data[16..20], offset);
+            data.append(&mut stringdata::to_ansi_z(
+                val.local_base_path.as_deref().unwrap_or_default(),
+            ));
+        }
+        if val
+            .link_info_flags
+            .contains(LinkInfoFlags::COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX)
+        {
+            let offset = data.len() as u32;
+            LE::write_u32(&mut data[20..24], offset);
+            data.append(&mut val.common_network_relative_link.unwrap_or_default().into());
         }
+        let offset = data.len() as u32;
+        LE::write_u32(&mut data[24..28], offset);
+        data.append(&mut stringdata::to_ansi_z(&val.common_path_suffix));
+
+        if has_unicode {
+            if let Some(local_base_path_unicode) = &val.local_base_path_unicode {
+                let offset = data.len() as u32;
+                LE::write_u32(&mut data[28..32], offset);
+                data.append(&mut stringdata::to_unicode_z(local_base_path_unicode));
+            }
+            let offset = data.len() as u32;
+            LE::write_u32(&mut data[32..36], offset);
+            data.append(&mut stringdata::to_unicode_z(
+                val.common_path_suffix_unicode
+                    .as_deref()
+                    .unwrap_or(&val.common_path_suffix),
+            ));
+        }
+
+        let size = data.len() as u32;
+        LE::write_u32(&mut data[0..4], size);
+        data
     }
 }
 
-impl Into<Vec<u8>> for LinkInfo {
-    fn into(self) -> Vec<u8> {
-        unimplemented!()
+/// Read the u32 offset at `pos` in a header and return it with the data it points to, or
+/// `None` if the offset is zero.
+fn data_at_offset<'a>(
+    data: &'a [u8],
+    pos: usize,
+    structure: &str,
+) -> Result<Option<(usize, &'a [u8])>, MSLinkError> {
+    let offset = LE::read_u32(&data[pos..]) as usize;
+    if offset == 0 {
+        return Ok(None);
+    }
+    data.get(offset..)
+        .map(|data| Some((offset, data)))
+        .ok_or_else(|| MSLinkError::truncated(structure).within(structure, offset))
+}
+
+impl TryFrom<&[u8]> for LinkInfo {
+    type Error = MSLinkError;
+
+    /// Read a LinkInfo structure, starting at its LinkInfoSize field.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 0x1c {
+            return Err(MSLinkError::truncated("LinkInfo"));
+        }
+        let size = LE::read_u32(data);
+        let data = data
+            .get(..size as usize)
+            .filter(|data| data.len() >= 0x1c)
+            .ok_or_else(|| MSLinkError::truncated("LinkInfo"))?;
+        let header_size = LE::read_u32(&data[4..]) as usize;
+        if header_size < 0x1c || data.len() < header_size {
+            return Err(MSLinkError::truncated("LinkInfo"));
+        }
+        let link_info_flags = LinkInfoFlags::from_bits_truncate(LE::read_u32(&data[8..]));
+
+        let mut link_info = Self {
+            size,
+            link_info_flags,
+            raw: Some(data.to_vec()),
+            ..Default::default()
+        };
+        if link_info_flags.contains(LinkInfoFlags::VOLUME_ID_AND_LOCAL_BASE_PATH) {
+            if let Some((offset, volume_id)) = data_at_offset(data, 12, "VolumeID")? {
+                link_info.volume_id =
+                    Some(VolumeId::try_from(volume_id).map_err(|e| e.within("VolumeID", offset))?);
+            }
+            if let Some((offset, local_base_path)) = data_at_offset(data, 16, "LocalBasePath")? {
+                link_info.local_base_path = Some(
+                    stringdata::parse_ansi_z(local_base_path, "LocalBasePath")
+                        .map(|(_, s)| s)
+                        .map_err(|e| e.within("LocalBasePath", offset))?,
+                );
+            }
+        }
+        if link_info_flags.contains(LinkInfoFlags::COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX) {
+            if let Some((offset, network_link)) =
+                data_at_offset(data, 20, "CommonNetworkRelativeLink")?
+            {
+                link_info.common_network_relative_link = Some(
+                    CommonNetworkRelativeLink::try_from(network_link)
+                        .map_err(|e| e.within("CommonNetworkRelativeLink", offset))?,
+                );
+            }
+        }
+        if let Some((offset, common_path_suffix)) = data_at_offset(data, 24, "CommonPathSuffix")? {
+            link_info.common_path_suffix =
+                stringdata::parse_ansi_z(common_path_suffix, "CommonPathSuffix")
+                    .map(|(_, s)| s)
+                    .map_err(|e| e.within("CommonPathSuffix", offset))?;
+        }
+        if header_size >= 0x24 {
+            if let Some((offset, local_base_path)) =
+                data_at_offset(data, 28, "LocalBasePathUnicode")?
+            {
+                link_info.local_base_path_unicode = Some(
+                    stringdata::parse_unicode_z(local_base_path, "LocalBasePathUnicode")
+                        .map_err(|e| e.within("LocalBasePathUnicode", offset))?,
+                );
+            }
+            if let Some((offset, common_path_suffix)) =
+                data_at_offset(data, 32, "CommonPathSuffixUnicode")?
+            {
+                link_info.common_path_suffix_unicode = Some(
+                    stringdata::parse_unicode_z(common_path_suffix, "CommonPathSuffixUnicode")
+                        .map_err(|e| e.within("CommonPathSuffixUnicode", offset))?,
+                );
+            }
+        }
+        Ok(link_info)
+    }
+}
+
+/// The VolumeID structure specifies information about the volume that a
+/// link target was on when the link was created. This information is useful
+/// for resolving the link if the file is not found in its original location.
+#[derive(Clone, Debug, Default)]
+pub struct VolumeId {
+    /// The type of drive the link target is stored on.
+    drive_type: DriveType,
+    /// The drive serial number of the volume the link target is stored on.
+    drive_serial_number: u32,
+    /// A NULL–terminated string, defined by the system default code page, that
+    /// specifies the volume label of the drive that the link target is stored
+    /// on.
+    volume_label: String,
+    /// An optional, NULL–terminated, Unicode string that specifies the volume
+    /// label. Present if VolumeLabelOffset is 0x00000014.
+    volume_label_unicode: Option<String>,
+}
+
+impl VolumeId {
+    /// Create a VolumeID for a drive.
+    pub fn new(drive_type: DriveType, drive_serial_number: u32, volume_label: String) -> Self {
+        let volume_label_unicode = if volume_label.is_ascii() {
+            None
+        } else {
+            Some(volume_label.clone())
+        };
+        Self {
+            drive_type,
+            drive_serial_number,
+            volume_label,
+            volume_label_unicode,
+        }
+    }
+
+    /// The type of drive the link target is stored on.
+    pub fn drive_type(&self) -> DriveType {
+        self.drive_type
+    }
+
+    /// The drive serial number of the volume the link target is stored on.
+    pub fn drive_serial_number(&self) -> u32 {
+        self.drive_serial_number
+    }
+
+    /// The volume label of the drive the link target is stored on.
+    pub fn volume_label(&self) -> &str {
+        self.volume_label_unicode
+            .as_deref()
+            .unwrap_or(&self.volume_label)
+    }
+}
+
+impl TryFrom<&[u8]> for VolumeId {
+    type Error = MSLinkError;
+
+    /// Read a VolumeID structure, starting at its VolumeIDSize field.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 0x10 {
+            return Err(MSLinkError::truncated("VolumeID"));
+        }
+        let size = LE::read_u32(data) as usize;
+        let data = data
+            .get(..size)
+            .filter(|data| data.len() >= 0x10)
+            .ok_or_else(|| MSLinkError::truncated("VolumeID"))?;
+        let drive_type = DriveType::from(LE::read_u32(&data[4..]));
+        let drive_serial_number = LE::read_u32(&data[8..]);
+        let label_offset = LE::read_u32(&data[12..]) as usize;
+        let mut volume_id = Self {
+            drive_type,
+            drive_serial_number,
+            ..Default::default()
+        };
+        if label_offset == 0x14 {
+            // the ANSI label is unused, VolumeLabelOffsetUnicode follows
+            if data.len() < 0x14 {
+                return Err(MSLinkError::truncated("VolumeID"));
+            }
+            let unicode_offset = LE::read_u32(&data[16..]) as usize;
+            let label = data.get(unicode_offset..).ok_or_else(|| {
+                MSLinkError::truncated("VolumeLabelUnicode")
+                    .within("VolumeLabelUnicode", unicode_offset)
+            })?;
+            volume_id.volume_label_unicode = Some(
+                stringdata::parse_unicode_z(label, "VolumeLabelUnicode")
+                    .map_err(|e| e.within("VolumeLabelUnicode", unicode_offset))?,
+            );
+        } else {
+            let label = data.get(label_offset..).ok_or_else(|| {
+                MSLinkError::truncated("VolumeLabel").within("VolumeLabel", label_offset)
+            })?;
+            volume_id.volume_label = stringdata::parse_ansi_z(label, "VolumeLabel")
+                .map(|(_, s)| s)
+                .map_err(|e| e.within("VolumeLabel", label_offset))?;
+        }
+        Ok(volume_id)
+    }
+}
+
+impl From<VolumeId> for Vec<u8> {
+    fn from(val: VolumeId) -> Self {
+        let mut data = vec![0u8; 0x10];
+        LE::write_u32(&mut data[4..8], val.drive_type.into());
+        LE::write_u32(&mut data[8..12], val.drive_serial_number);
+        if let Some(volume_label_unicode) = &val.volume_label_unicode {
+            // VolumeLabelOffset 0x14 means VolumeLabelOffsetUnicode is used instead
+            LE::write_u32(&mut data[12..16], 0x14);
+            data.append(&mut vec![0u8; 4]);
+            LE::write_u32(&mut data[16..20], 0x14);
+            data.append(&mut stringdata::to_unicode_z(volume_label_unicode));
+        } else {
+            LE::write_u32(&mut data[12..16], 0x10);
+            data.append(&mut stringdata::to_ansi_z(&val.volume_label));
+        }
+        let size = data.len() as u32;
+        LE::write_u32(&mut data[0..4], size);
+        data
+    }
+}
+
+/// The type of drive the link target is stored on.
+///
+/// Values that are not known to this crate are kept in [`Other`](#variant.Other), so they
+/// are written back unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DriveType {
+    /// The drive type cannot be determined.
+    #[default]
+    DriveUnknown,
+    /// The root path is invalid; for example, there is no volume mounted at the path.
+    DriveNoRootDir,
+    /// The drive has removable media, such as a floppy drive, thumb drive, or flash card reader.
+    DriveRemovable,
+    /// The drive has fixed media, such as a hard drive or flash drive.
+    DriveFixed,
+    /// The drive is a remote (network) drive.
+    DriveRemote,
+    /// The drive is a CD-ROM drive.
+    DriveCdrom,
+    /// The drive is a RAM disk.
+    DriveRamdisk,
+    /// A drive type not known to this crate.
+    Other(u32),
+}
+
+impl From<u32> for DriveType {
+    /// Never fails, unknown values become [`Other`](#variant.Other). This also provides
+    /// `TryFrom<u32>`.
+    fn from(value: u32) -> Self {
+        match value {
+            0x00 => Self::DriveUnknown,
+            0x01 => Self::DriveNoRootDir,
+            0x02 => Self::DriveRemovable,
+            0x03 => Self::DriveFixed,
+            0x04 => Self::DriveRemote,
+            0x05 => Self::DriveCdrom,
+            0x06 => Self::DriveRamdisk,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<DriveType> for u32 {
+    fn from(val: DriveType) -> Self {
+        match val {
+            DriveType::DriveUnknown => 0x00,
+            DriveType::DriveNoRootDir => 0x01,
+            DriveType::DriveRemovable => 0x02,
+            DriveType::DriveFixed => 0x03,
+            DriveType::DriveRemote => 0x04,
+            DriveType::DriveCdrom => 0x05,
+            DriveType::DriveRamdisk => 0x06,
+            DriveType::Other(value) => value,
+        }
     }
 }
 
 bitflags! {
+    /// Flags that specify whether the VolumeID, LocalBasePath, LocalBasePathUnicode, and
+    /// CommonNetworkRelativeLink fields are present in a LinkInfo structure.
     pub struct LinkInfoFlags: u32 {
         /// If set, the VolumeIDand LocalBasePath fields are present, and their
         /// locations are specified by the values of the VolumeIDOffset and
@@ -86,6 +517,8 @@ bitflags! {
     }
 }
 
+/// The CommonNetworkRelativeLink structure specifies information about the network location
+/// where a link target is stored, including the mapped drive letter and the UNC path prefix.
 #[derive(Clone, Debug)]
 pub struct CommonNetworkRelativeLink {
     /// Flags that specify the contents of the DeviceNameOffset and
@@ -127,9 +560,180 @@ impl Default for CommonNetworkRelativeLink {
     }
 }
 
-impl Into<Vec<u8>> for CommonNetworkRelativeLink {
-    fn into(self) -> Vec<u8> {
-        unimplemented!()
+impl CommonNetworkRelativeLink {
+    /// Create a CommonNetworkRelativeLink for a share such as `\\server\share`.
+    pub fn new(net_name: String, network_provider_type: Option<NetworkProviderType>) -> Self {
+        let mut flags = CommonNetworkRelativeLinkFlags::empty();
+        flags.set(
+            CommonNetworkRelativeLinkFlags::VALID_NET_TYPE,
+            network_provider_type.is_some(),
+        );
+        let net_name_unicode = if net_name.is_ascii() {
+            None
+        } else {
+            Some(net_name.clone())
+        };
+        Self {
+            flags,
+            network_provider_type,
+            net_name,
+            net_name_unicode,
+            ..Default::default()
+        }
+    }
+
+    /// Set the device a share is mapped to, e.g. the drive letter `Z:`. This updates the
+    /// ValidDevice flag.
+    pub fn set_device_name(&mut self, device_name: Option<String>) {
+        self.flags.set(
+            CommonNetworkRelativeLinkFlags::VALID_DEVICE,
+            device_name.is_some(),
+        );
+        let device_name = device_name.unwrap_or_default();
+        self.device_name_unicode = if device_name.is_ascii() {
+            None
+        } else {
+            Some(device_name.clone())
+        };
+        self.device_name = device_name;
+    }
+
+    /// Flags that specify the contents of the DeviceNameOffset and NetProviderType fields.
+    pub fn flags(&self) -> &CommonNetworkRelativeLinkFlags {
+        &self.flags
+    }
+
+    /// The type of network provider, if the ValidNetType flag is set.
+    pub fn network_provider_type(&self) -> Option<&NetworkProviderType> {
+        self.network_provider_type.as_ref()
+    }
+
+    /// The server share path, e.g. `\\server\share`.
+    pub fn net_name(&self) -> &str {
+        &self.net_name
+    }
+
+    /// The device, e.g. the drive letter `D:`, if the ValidDevice flag is set.
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// The Unicode version of the net name, if present.
+    pub fn net_name_unicode(&self) -> Option<&str> {
+        self.net_name_unicode.as_deref()
+    }
+
+    /// The Unicode version of the device name, if present.
+    pub fn device_name_unicode(&self) -> Option<&str> {
+        self.device_name_unicode.as_deref()
+    }
+}
+
+impl From<CommonNetworkRelativeLink> for Vec<u8> {
+    fn from(val: CommonNetworkRelativeLink) -> Self {
+        // the Unicode offsets are only present if NetNameOffset is greater than 0x14
+        let has_unicode = val.net_name_unicode.is_some() || val.device_name_unicode.is_some();
+        let header_size = if has_unicode { 0x1c } else { 0x14 };
+        let has_device = val
+            .flags
+            .contains(CommonNetworkRelativeLinkFlags::VALID_DEVICE);
+        let mut data = vec![0u8; header_size];
+        LE::write_u32(&mut data[4..8], val.flags.bits);
+        if val
+            .flags
+            .contains(CommonNetworkRelativeLinkFlags::VALID_NET_TYPE)
+        {
+            if let Some(network_provider_type) = val.network_provider_type {
+                LE::write_u32(&mut data[16..20], network_provider_type.into());
+            }
+        }
+
+        let offset = data.len() as u32;
+        LE::write_u32(&mut data[8..12], offset);
+        data.append(&mut stringdata::to_ansi_z(&val.net_name));
+        if has_device {
+            let offset = data.len() as u32;
+            LE::write_u32(&mut data[12..16], offset);
+            data.append(&mut stringdata::to_ansi_z(&val.device_name));
+        }
+        if has_unicode {
+            let offset = data.len() as u32;
+            LE::write_u32(&mut data[20..24], offset);
+            data.append(&mut stringdata::to_unicode_z(
+                val.net_name_unicode.as_deref().unwrap_or(&val.net_name),
+            ));
+            if has_device {
+                let offset = data.len() as u32;
+                LE::write_u32(&mut data[24..28], offset);
+                data.append(&mut stringdata::to_unicode_z(
+                    val.device_name_unicode
+                        .as_deref()
+                        .unwrap_or(&val.device_name),
+                ));
+            }
+        }
+
+        let size = data.len() as u32;
+        LE::write_u32(&mut data[0..4], size);
+        data
+    }
+}
+
+impl TryFrom<&[u8]> for CommonNetworkRelativeLink {
+    type Error = MSLinkError;
+
+    /// Read a CommonNetworkRelativeLink structure, starting at its
+    /// CommonNetworkRelativeLinkSize field.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 0x14 {
+            return Err(MSLinkError::truncated("CommonNetworkRelativeLink"));
+        }
+        let size = LE::read_u32(data) as usize;
+        let data = data
+            .get(..size)
+            .filter(|data| data.len() >= 0x14)
+            .ok_or_else(|| MSLinkError::truncated("CommonNetworkRelativeLink"))?;
+        let flags = CommonNetworkRelativeLinkFlags::from_bits_truncate(LE::read_u32(&data[4..]));
+        let net_name_offset = LE::read_u32(&data[8..]) as usize;
+
+        let mut network_link = Self {
+            flags,
+            ..Default::default()
+        };
+        if flags.contains(CommonNetworkRelativeLinkFlags::VALID_NET_TYPE) {
+            network_link.network_provider_type =
+                Some(NetworkProviderType::from(LE::read_u32(&data[16..])));
+        }
+        if let Some((offset, net_name)) = data_at_offset(data, 8, "NetName")? {
+            network_link.net_name = stringdata::parse_ansi_z(net_name, "NetName")
+                .map(|(_, s)| s)
+                .map_err(|e| e.within("NetName", offset))?;
+        }
+        if flags.contains(CommonNetworkRelativeLinkFlags::VALID_DEVICE) {
+            if let Some((offset, device_name)) = data_at_offset(data, 12, "DeviceName")? {
+                network_link.device_name = stringdata::parse_ansi_z(device_name, "DeviceName")
+                    .map(|(_, s)| s)
+                    .map_err(|e| e.within("DeviceName", offset))?;
+            }
+        }
+        if net_name_offset > 0x14 {
+            if data.len() < 0x1c {
+                return Err(MSLinkError::truncated("CommonNetworkRelativeLink"));
+            }
+            if let Some((offset, net_name)) = data_at_offset(data, 20, "NetNameUnicode")? {
+                network_link.net_name_unicode = Some(
+                    stringdata::parse_unicode_z(net_name, "NetNameUnicode")
+                        .map_err(|e| e.within("NetNameUnicode", offset))?,
+                );
+            }
+            if let Some((offset, device_name)) = data_at_offset(data, 24, "DeviceNameUnicode")? {
+                network_link.device_name_unicode = Some(
+                    stringdata::parse_unicode_z(device_name, "DeviceNameUnicode")
+                        .map_err(|e| e.within("DeviceNameUnicode", offset))?,
+                );
+            }
+        }
+        Ok(network_link)
     }
 }
 
@@ -147,47 +751,289 @@ bitflags! {
     }
 }
 
-#[derive(Clone, Debug)]
-pub enum NetworkProviderType {
-    Avid = 0x1a0000,
-    Docuspace = 0x1b0000,
-    Mangosoft = 0x1c0000,
-    Sernet = 0x1d0000,
-    Riverfront1 = 0x1e0000,
-    Riverfront2 = 0x1f0000,
-    Decorb = 0x200000,
-    Protstor = 0x210000,
-    FjRedir = 0x220000,
-    Distinct = 0x230000,
-    Twins = 0x240000,
-    Rdr2Sample = 0x250000,
-    CSC = 0x260000,
-    _3In1 = 0x270000,
-    ExtendNet = 0x290000,
-    Stac = 0x2a0000,
-    Foxbat = 0x2b0000,
-    Yahoo = 0x2c0000,
-    Exifs = 0x2d0000,
-    Dav = 0x2e0000,
-    Knoware = 0x2f0000,
-    ObjectDire = 0x300000,
-    Masfax = 0x310000,
-    HobNfs = 0x320000,
-    Shiva = 0x330000,
-    Ibmal = 0x340000,
-    Lock = 0x350000,
-    Termsrv = 0x360000,
-    Srt = 0x370000,
-    Quincy = 0x380000,
-    Openafs = 0x390000,
-    Avid1 = 0x3a0000,
-    Dfs = 0x3b0000,
-    Kwnp = 0x3c0000,
-    Zenworks = 0x3d0000,
-    Driveonweb = 0x3e0000,
-    Vmware = 0x3f0000,
-    Rsfx = 0x400000,
-    Mfiles = 0x410000,
-    MsNfs = 0x420000,
-    Google = 0x430000,
+macro_rules! network_provider_types {
+    ($($(#[$meta:meta])* $variant:ident = $value:expr => $name:expr,)*) => {
+        /// The type of network provider, as stored in the NetworkProviderType field of a
+        /// CommonNetworkRelativeLink.
+        ///
+        /// Values that are not known to this crate are kept in [`Other`](#variant.Other), so
+        /// they are written back unchanged.
+        #[allow(clippy::upper_case_acronyms)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum NetworkProviderType {
+            $($(#[$meta])* $variant,)*
+            /// A network provider type not known to this crate.
+            Other(u32),
+        }
+
+        impl From<u32> for NetworkProviderType {
+            /// Never fails, unknown values become [`Other`](#variant.Other). This also
+            /// provides `TryFrom<u32>`.
+            fn from(value: u32) -> Self {
+                match value {
+                    $($value => Self::$variant,)*
+                    _ => Self::Other(value),
+                }
+            }
+        }
+
+        impl From<NetworkProviderType> for u32 {
+            fn from(val: NetworkProviderType) -> Self {
+                match val {
+                    $(NetworkProviderType::$variant => $value,)*
+                    NetworkProviderType::Other(value) => value,
+                }
+            }
+        }
+
+        impl fmt::Display for NetworkProviderType {
+            /// The WNNC_NET_* name of the provider type, or its value in hex if unknown.
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    $(Self::$variant => f.write_str($name),)*
+                    Self::Other(value) => write!(f, "0x{:08x}", value),
+                }
+            }
+        }
+    };
+}
+
+network_provider_types! {
+    /// Microsoft Windows Network (SMB). Not listed by MS-SHLLINK, but
+    /// written by Windows for every SMB share.
+    Lanman = 0x020000 => "WNNC_NET_LANMAN",
+    Avid = 0x1a0000 => "WNNC_NET_AVID",
+    Docuspace = 0x1b0000 => "WNNC_NET_DOCUSPACE",
+    Mangosoft = 0x1c0000 => "WNNC_NET_MANGOSOFT",
+    Sernet = 0x1d0000 => "WNNC_NET_SERNET",
+    Riverfront1 = 0x1e0000 => "WNNC_NET_RIVERFRONT1",
+    Riverfront2 = 0x1f0000 => "WNNC_NET_RIVERFRONT2",
+    Decorb = 0x200000 => "WNNC_NET_DECORB",
+    Protstor = 0x210000 => "WNNC_NET_PROTSTOR",
+    FjRedir = 0x220000 => "WNNC_NET_FJ_REDIR",
+    Distinct = 0x230000 => "WNNC_NET_DISTINCT",
+    Twins = 0x240000 => "WNNC_NET_TWINS",
+    Rdr2Sample = 0x250000 => "WNNC_NET_RDR2SAMPLE",
+    CSC = 0x260000 => "WNNC_NET_CSC",
+    _3In1 = 0x270000 => "WNNC_NET_3IN1",
+    ExtendNet = 0x290000 => "WNNC_NET_EXTENDNET",
+    Stac = 0x2a0000 => "WNNC_NET_STAC",
+    Foxbat = 0x2b0000 => "WNNC_NET_FOXBAT",
+    Yahoo = 0x2c0000 => "WNNC_NET_YAHOO",
+    Exifs = 0x2d0000 => "WNNC_NET_EXIFS",
+    Dav = 0x2e0000 => "WNNC_NET_DAV",
+    Knoware = 0x2f0000 => "WNNC_NET_KNOWARE",
+    ObjectDire = 0x300000 => "WNNC_NET_OBJECT_DIRE",
+    Masfax = 0x310000 => "WNNC_NET_MASFAX",
+    HobNfs = 0x320000 => "WNNC_NET_HOB_NFS",
+    Shiva = 0x330000 => "WNNC_NET_SHIVA",
+    Ibmal = 0x340000 => "WNNC_NET_IBMAL",
+    Lock = 0x350000 => "WNNC_NET_LOCK",
+    Termsrv = 0x360000 => "WNNC_NET_TERMSRV",
+    Srt = 0x370000 => "WNNC_NET_SRT",
+    Quincy = 0x380000 => "WNNC_NET_QUINCY",
+    Openafs = 0x390000 => "WNNC_NET_OPENAFS",
+    Avid1 = 0x3a0000 => "WNNC_NET_AVID1",
+    Dfs = 0x3b0000 => "WNNC_NET_DFS",
+    Kwnp = 0x3c0000 => "WNNC_NET_KWNP",
+    Zenworks = 0x3d0000 => "WNNC_NET_ZENWORKS",
+    Driveonweb = 0x3e0000 => "WNNC_NET_DRIVEONWEB",
+    Vmware = 0x3f0000 => "WNNC_NET_VMWARE",
+    Rsfx = 0x400000 => "WNNC_NET_RSFX",
+    Mfiles = 0x410000 => "WNNC_NET_MFILES",
+    MsNfs = 0x420000 => "WNNC_NET_MS_NFS",
+    Google = 0x430000 => "WNNC_NET_GOOGLE",
+}
+
+#[test]
+fn test_network_link_info_to_data() {
+    let network_link = CommonNetworkRelativeLink::new(
+        r"\\fileserver\share".to_owned(),
+        Some(NetworkProviderType::Lanman),
+    );
+    let data: Vec<u8> = LinkInfo::new_network(network_link, r"tools\tool.exe".to_owned()).into();
+
+    assert_eq!(LE::read_u32(&data[0..]) as usize, data.len());
+    assert_eq!(LE::read_u32(&data[4..]), 0x1c);
+    assert_eq!(
+        LE::read_u32(&data[8..]),
+        LinkInfoFlags::COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX.bits
+    );
+    // no VolumeID and LocalBasePath
+    assert_eq!(LE::read_u32(&data[12..]), 0);
+    assert_eq!(LE::read_u32(&data[16..]), 0);
+
+    let cnrl = &data[LE::read_u32(&data[20..]) as usize..];
+    let cnrl_size = LE::read_u32(cnrl) as usize;
+    assert_eq!(
+        LE::read_u32(&cnrl[4..]),
+        CommonNetworkRelativeLinkFlags::VALID_NET_TYPE.bits
+    );
+    assert_eq!(LE::read_u32(&cnrl[8..]), 0x14);
+    assert_eq!(LE::read_u32(&cnrl[12..]), 0);
+    assert_eq!(LE::read_u32(&cnrl[16..]), 0x020000);
+    assert_eq!(&cnrl[0x14..cnrl_size], b"\\\\fileserver\\share\0");
+
+    let suffix_offset = LE::read_u32(&data[24..]) as usize;
+    assert_eq!(suffix_offset, 0x1c + cnrl_size);
+    assert_eq!(&data[suffix_offset..], b"tools\\tool.exe\0");
+}
+
+#[test]
+fn test_network_link_info_unicode() {
+    let network_link = CommonNetworkRelativeLink::new(r"\\服务器\share".to_owned(), None);
+    assert_eq!(network_link.net_name_unicode(), Some(r"\\服务器\share"));
+    let cnrl: Vec<u8> = network_link.clone().into();
+    assert_eq!(LE::read_u32(&cnrl[4..]), 0);
+    assert_eq!(LE::read_u32(&cnrl[8..]), 0x1c);
+    assert_eq!(LE::read_u32(&cnrl[16..]), 0);
+    let unicode_offset = LE::read_u32(&cnrl[20..]) as usize;
+    assert_eq!(
+        &cnrl[unicode_offset..],
+        stringdata::to_unicode_z(r"\\服务器\share").as_slice()
+    );
+
+    let data: Vec<u8> = LinkInfo::new_network(network_link, "文档".to_owned()).into();
+    assert_eq!(LE::read_u32(&data[4..]), 0x24);
+    let suffix_unicode_offset = LE::read_u32(&data[32..]) as usize;
+    assert_eq!(
+        &data[suffix_unicode_offset..],
+        stringdata::to_unicode_z("文档").as_slice()
+    );
+}
+
+#[test]
+fn test_mapped_drive_network_link_to_data() {
+    let mut network_link = CommonNetworkRelativeLink::new(
+        r"\\nas\builds".to_owned(),
+        Some(NetworkProviderType::Lanman),
+    );
+    network_link.set_device_name(Some("Z:".to_owned()));
+    let cnrl: Vec<u8> = network_link.into();
+
+    assert_eq!(LE::read_u32(&cnrl[0..]) as usize, cnrl.len());
+    assert_eq!(
+        LE::read_u32(&cnrl[4..]),
+        (CommonNetworkRelativeLinkFlags::VALID_DEVICE
+            | CommonNetworkRelativeLinkFlags::VALID_NET_TYPE)
+            .bits
+    );
+    assert_eq!(LE::read_u32(&cnrl[8..]), 0x14);
+    let device_name_offset = LE::read_u32(&cnrl[12..]) as usize;
+    assert_eq!(device_name_offset, 0x14 + r"\\nas\builds".len() + 1);
+    assert_eq!(&cnrl[device_name_offset..], b"Z:\0");
+    assert_eq!(LE::read_u32(&cnrl[16..]), 0x020000);
+}
+
+#[test]
+fn test_long_local_link_info() {
+    let short: Vec<u8> = LinkInfo::new_local(VolumeId::default(), r"C:\app.exe".to_owned()).into();
+    assert_eq!(LE::read_u32(&short[4..]), 0x1c);
+
+    let long_path = format!(r"C:\{}\app.exe", "a".repeat(300));
+    let data: Vec<u8> = LinkInfo::new_local(VolumeId::default(), long_path.clone()).into();
+    assert_eq!(LE::read_u32(&data[4..]), 0x24);
+    let link_info = LinkInfo::try_from(data.as_slice()).unwrap();
+    assert_eq!(link_info.local_base_path(), Some(long_path.as_str()));
+    assert_eq!(
+        link_info.local_base_path_unicode(),
+        Some(long_path.as_str())
+    );
+    assert_eq!(link_info.common_path_suffix_unicode(), Some(""));
+    assert_eq!(
+        link_info.volume_id().unwrap().drive_type(),
+        DriveType::DriveUnknown
+    );
+    let resaved: Vec<u8> = link_info.into();
+    assert_eq!(resaved, data);
+}
+
+#[test]
+fn test_network_provider_type_conversions() {
+    assert_eq!(
+        NetworkProviderType::from(0x020000),
+        NetworkProviderType::Lanman
+    );
+    assert_eq!(u32::from(NetworkProviderType::Google), 0x430000);
+    assert_eq!(
+        NetworkProviderType::from(0x440000),
+        NetworkProviderType::Other(0x440000)
+    );
+    assert_eq!(u32::from(NetworkProviderType::Other(0x440000)), 0x440000);
+    assert_eq!(NetworkProviderType::Dav.to_string(), "WNNC_NET_DAV");
+    assert_eq!(
+        NetworkProviderType::Other(0x440000).to_string(),
+        "0x00440000"
+    );
+}
+
+#[test]
+fn test_network_link_info_round_trip() {
+    let mut network_link = CommonNetworkRelativeLink::new(
+        r"\\redirector\share".to_owned(),
+        Some(NetworkProviderType::Other(0x00ab0000)),
+    );
+    network_link.set_device_name(Some("Y:".to_owned()));
+    let data: Vec<u8> = LinkInfo::new_network(network_link, r"bin\tool.exe".to_owned()).into();
+
+    let link_info = LinkInfo::try_from(data.as_slice()).unwrap();
+    assert_eq!(link_info.size as usize, data.len());
+    assert_eq!(link_info.common_path_suffix(), r"bin\tool.exe");
+    let network_link = link_info.common_network_relative_link().unwrap();
+    assert_eq!(network_link.net_name(), r"\\redirector\share");
+    assert_eq!(network_link.device_name(), "Y:");
+    assert_eq!(
+        network_link.network_provider_type(),
+        Some(&NetworkProviderType::Other(0x00ab0000))
+    );
+
+    let resaved: Vec<u8> = link_info.into();
+    assert_eq!(resaved, data);
+}
+
+#[test]
+fn test_unicode_network_link_round_trip() {
+    let mut network_link = CommonNetworkRelativeLink::new(r"\\服务器\共享".to_owned(), None);
+    network_link.set_device_name(Some("Z:".to_owned()));
+    let data: Vec<u8> = LinkInfo::new_network(network_link, "文档".to_owned()).into();
+
+    let link_info = LinkInfo::try_from(data.as_slice()).unwrap();
+    let network_link = link_info.common_network_relative_link().unwrap();
+    assert_eq!(network_link.net_name_unicode(), Some(r"\\服务器\共享"));
+    assert_eq!(network_link.network_provider_type(), None);
+    assert_eq!(link_info.common_path_suffix_unicode(), Some("文档"));
+    let resaved: Vec<u8> = link_info.into();
+    assert_eq!(resaved, data);
+}
+
+#[test]
+fn test_volume_id_round_trip() {
+    for label in &["Windows", "系统"] {
+        let data: Vec<u8> =
+            VolumeId::new(DriveType::DriveFixed, 0x1234abcd, label.to_string()).into();
+        let volume_id = VolumeId::try_from(data.as_slice()).unwrap();
+        assert_eq!(volume_id.drive_type(), DriveType::DriveFixed);
+        assert_eq!(volume_id.drive_serial_number(), 0x1234abcd);
+        assert_eq!(volume_id.volume_label(), *label);
+        let resaved: Vec<u8> = volume_id.into();
+        assert_eq!(resaved, data);
+    }
+
+    // drive types unknown to this crate are kept
+    let mut data: Vec<u8> =
+        VolumeId::new(DriveType::DriveFixed, 0x1234abcd, "Windows".to_owned()).into();
+    LE::write_u32(&mut data[4..], 7);
+    let volume_id = VolumeId::try_from(data.as_slice()).unwrap();
+    assert_eq!(volume_id.drive_type(), DriveType::Other(7));
+    let resaved: Vec<u8> = volume_id.into();
+    assert_eq!(resaved, data);
+}
+
+#[test]
+fn test_truncated_link_info() {
+    let network_link = CommonNetworkRelativeLink::new(r"\\server\share".to_owned(), None);
+    let data: Vec<u8> = LinkInfo::new_network(network_link, String::new()).into();
+    for len in 0..data.len() {
+        assert!(LinkInfo::try_from(&data[..len]).is_err());
+    }
 }
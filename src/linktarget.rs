@@ -1,26 +1,12 @@
+use crate::shellitem::{FileEntryItem, NetworkLocationItem, RootFolderItem, VolumeItem};
+use crate::{filetime, MSLinkError, RootFolder, ShellItem, WindowsVersion};
 use byteorder::{ByteOrder, LE};
-use std::{
-    ffi::OsString, mem::size_of, os::windows::ffi::OsStrExt, path::Path, ptr::null_mut, rc::Rc,
-};
-/// computer item id (this pc on win10)
-const ROOT_FOLDER_SHELL: [u8; 20] = [
-    0x14, 0x00, 0x1F, 0x50, 0xE0, 0x4F, 0xD0, 0x20, 0xEA, 0x3A, 0x69, 0x10, 0xA2, 0xD8, 0x08, 0x00,
-    0x2B, 0x30, 0x30, 0x9D,
-];
+use std::{convert::TryFrom, fmt, fs, path::Path};
 
 /// The LinkTargetIDList structure specifies the target of the link. The presence of this optional
 /// structure is specified by the HasLinkTargetIDList bit (LinkFlagssection 2.1.1) in the
 /// ShellLinkHeader(section2.1).
-fn get_driveitemid(driveletter: char) -> [u8; 25] {
-    let mut drive = [
-        0x19, 0x00, 0x2f, b'C', b':', b'\\', 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    ];
-    // fix drive letter
-    drive[3] = driveletter.to_string().as_bytes()[0];
-    drive
-}
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct LinkTargetIdList {
     /// The size, in bytes, of the IDList field.
     pub size: u16,
@@ -28,240 +14,470 @@ pub struct LinkTargetIdList {
     /// structure conforms to the following ABNF [RFC5234]:
     ///   `IDLIST = *ITEMID TERMINALID`
     id_list: Vec<ItemID>,
+    /// Data following the TerminalID within IDListSize, kept so parsed lists are written back
+    /// unchanged.
+    padding: Vec<u8>,
 }
 
-impl Default for LinkTargetIdList {
-    fn default() -> Self {
-        Self {
-            size: 0,
-            id_list: Vec::new(),
-        }
-    }
+/// Where a [`TargetPath`] is rooted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum TargetRoot {
+    /// A local or mapped drive, with its upper-case letter.
+    Drive(char),
+    /// A UNC share, `\\server\share`.
+    Unc { server: String, share: String },
+}
+
+/// A Windows path split into its root and components.
+///
+/// Paths are parsed by hand instead of through `std::path` so that Windows targets can be
+/// handled on any host. Both `\` and `/` are accepted as separators, and empty components
+/// (doubled or trailing separators) are dropped, so `C:`, `C:\` and `C:\\` all name the
+/// drive root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TargetPath {
+    /// The drive or share the path is rooted at.
+    pub(crate) root: TargetRoot,
+    /// The path components below the drive or share root.
+    pub(crate) components: Vec<String>,
 }
-fn return_fattrflag_clstpind(
-    long_name: &str,
-    counter: usize,
-    total_long_parts: usize,
-) -> (u16, u8) {
-    if counter + 1 < total_long_parts {
-        //   0x01 is_dir
-        if long_name.is_ascii() {
-            (0x0010u16, 0x31)
-        } else {
-            // 0x01 is_dir and 0x04 has unicode string
-            (0x0010u16, 0x35)
+
+impl TargetPath {
+    /// Parse a [`Path`] holding an absolute Windows path, see [`TargetPath::parse`].
+    pub(crate) fn from_path(target: &Path) -> Result<Self, MSLinkError> {
+        let target = target.to_str().ok_or_else(|| {
+            MSLinkError::unsupported_path(&target.to_string_lossy(), "is not valid unicode")
+        })?;
+        Self::parse(target)
+    }
+
+    /// Parse an absolute, drive-letter based or UNC Windows path. Long path prefixes
+    /// (`\\?\C:\` and `\\?\UNC\server\share`) are accepted and dropped, as they only
+    /// disable path normalization and are not stored in links.
+    pub(crate) fn parse(target: &str) -> Result<Self, MSLinkError> {
+        let unprefixed;
+        let target = match target.get(..8) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("\\\\?\\UNC\\") => {
+                unprefixed = format!("\\\\{}", &target[8..]);
+                unprefixed.as_str()
+            }
+            _ => target.strip_prefix("\\\\?\\").unwrap_or(target),
+        };
+        if let Some(unc) = target
+            .strip_prefix("\\\\")
+            .or_else(|| target.strip_prefix("//"))
+        {
+            let mut parts = unc.split(is_separator).filter(|part| !part.is_empty());
+            let (server, share) = match (parts.next(), parts.next()) {
+                (Some(server), Some(share)) => (server.to_owned(), share.to_owned()),
+                _ => {
+                    return Err(MSLinkError::unsupported_path(
+                        target,
+                        "does not name a server and share",
+                    ))
+                }
+            };
+            return Ok(Self {
+                root: TargetRoot::Unc { server, share },
+                components: parts.map(str::to_owned).collect(),
+            });
         }
-    } else {
-        if long_name.is_ascii() {
-            // is_file
-            (0x0020, 0x32u8)
-        } else {
-            // is_file and has unicode
-            (0x0020, 0x36u8)
+
+        let mut chars = target.chars();
+        let drive = match (chars.next(), chars.next()) {
+            (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+                letter.to_ascii_uppercase()
+            }
+            _ => {
+                return Err(MSLinkError::unsupported_path(
+                    target,
+                    "is not an absolute drive or UNC path",
+                ))
+            }
+        };
+        let rest = chars.as_str();
+        if !rest.is_empty() && !rest.starts_with(is_separator) {
+            return Err(MSLinkError::unsupported_path(
+                target,
+                "is relative to the current directory of its drive",
+            ));
         }
+        let components = rest
+            .split(is_separator)
+            .filter(|part| !part.is_empty())
+            .map(str::to_owned)
+            .collect();
+        Ok(Self {
+            root: TargetRoot::Drive(drive),
+            components,
+        })
     }
-}
-impl LinkTargetIdList {
-    pub fn set_linktarget<P: AsRef<Path>>(&mut self, target: P) {
-        let target = target.as_ref().to_owned().to_str().unwrap().to_owned();
-        // root folder shell item this pc
-        let itemid_computer = ItemID::from(ROOT_FOLDER_SHELL.to_vec().as_slice());
-        self.id_list.push(itemid_computer);
-
-        // dirve_item
-        let drive_letter = target.chars().nth(0).unwrap();
-        let drive = get_driveitemid(drive_letter);
-        let itemid_drive = ItemID::from(drive.to_vec().as_slice());
-        self.id_list.push(itemid_drive);
-        // File entry shell item
-        let mut long_pathparts = Path::new(&target)
-            .iter()
-            .map(|e| e.to_str().unwrap())
-            .collect::<Vec<_>>();
-        // remove \
-        long_pathparts.remove(1);
-        let num_filesystemobjects = long_pathparts.len();
-        let mut c = 1;
-        while c < num_filesystemobjects {
-            let long_item = long_pathparts.get(c).unwrap().to_owned();
-            let (fattr, class_type_indicator) =
-                return_fattrflag_clstpind(long_item, c, num_filesystemobjects);
-            // extension block
-            let mut fileitem = FileEntryItem::default();
-            fileitem.extension_block.version = 0x0008;
-            fileitem.extension_block.signature = 0xbeef0004;
-            fileitem.extension_block.ctime = 0;
-            fileitem.extension_block.atime = 0;
-            // win7+
-            fileitem.extension_block.unknown_ver_id = 0x002a;
-            fileitem.extension_block.unknown_emp = 0;
-            fileitem.extension_block.first_offset = 0x0014;
-            // construct itemid
-            // folder or file
-            fileitem.class_type_indicator = class_type_indicator;
-            // file attr
-            fileitem.file_attrbute_flags = fattr;
-            // path parts : test
-            fileitem.entry_name = long_item.to_owned();
-            fileitem
-                .extension_block
-                .set_size(fileitem.return_entry_name_wide_vec().len() as u16);
-            fileitem.set_size();
-            let item_vec: Vec<u8> = fileitem.into();
-            let itemid = ItemID::from(item_vec.as_slice());
-            self.id_list.push(itemid);
-            c += 1;
+
+    /// The drive or share root, e.g. `C:\` or `\\server\share\`.
+    pub(crate) fn root(&self) -> String {
+        match &self.root {
+            TargetRoot::Drive(drive) => format!("{}:\\", drive),
+            TargetRoot::Unc { server, share } => format!("\\\\{}\\{}\\", server, share),
         }
-        // sum up itemid.size
-        let mut idlist_size = 2u16;
-        for itemid in &self.id_list {
-            idlist_size += itemid.size
+    }
+
+    /// The last component, or `None` for the drive or share root.
+    pub(crate) fn file_name(&self) -> Option<&str> {
+        self.components.last().map(String::as_str)
+    }
+
+    /// The containing directory, or `None` for the drive or share root. The parent of a file
+    /// directly in the root is the root itself (`C:\`), any other parent has no trailing
+    /// separator.
+    pub(crate) fn parent(&self) -> Option<String> {
+        match self.components.len() {
+            0 => None,
+            1 => Some(self.root()),
+            n => Some(format!(
+                "{}{}",
+                self.root(),
+                self.components[..n - 1].join("\\")
+            )),
         }
-        self.size = idlist_size;
+    }
+
+    /// The full path, without a long path prefix.
+    pub(crate) fn path(&self) -> String {
+        format!("{}{}", self.root(), self.suffix())
+    }
+
+    /// The path below the root, joined with `\`.
+    pub(crate) fn suffix(&self) -> String {
+        self.components.join("\\")
     }
 }
 
-impl Into<Vec<u8>> for LinkTargetIdList {
-    fn into(self) -> Vec<u8> {
-        let mut data = vec![0, 0];
-        LE::write_u16(&mut data[0..2], self.size);
-        for id in self.id_list {
-            let mut other_data = id.into();
-            data.append(&mut other_data);
+/// The size, times and short name of a target or one of its parent directories, stored in its
+/// file entry item. Times are FILETIMEs, zero if unknown.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ItemMetadata {
+    pub(crate) file_size: u64,
+    pub(crate) creation_time: u64,
+    pub(crate) access_time: u64,
+    pub(crate) write_time: u64,
+    /// The 8.3 short name, generated from the long name if unknown.
+    pub(crate) short_name: Option<String>,
+}
+
+impl ItemMetadata {
+    /// Take the size and times from file system metadata. Times the platform doesn't record
+    /// are left zero.
+    pub(crate) fn from_metadata(meta: &fs::Metadata) -> Self {
+        let time = |time: std::io::Result<std::time::SystemTime>| {
+            time.map_or(0, filetime::from_system_time)
+        };
+        Self {
+            file_size: meta.len(),
+            creation_time: time(meta.created()),
+            access_time: time(meta.accessed()),
+            write_time: time(meta.modified()),
+            short_name: None,
         }
-        // add terminal id 0000
-        data.append(&mut 0u16.to_le_bytes().to_vec());
-        data
+    }
+
+    /// Read the metadata of the last `count` components of `target`, from the topmost down to
+    /// the target itself. Components that can't be read get no size or times.
+    pub(crate) fn read_components(target: &Path, count: usize) -> Vec<Self> {
+        let mut components: Vec<Self> = target
+            .ancestors()
+            .take(count)
+            .map(|path| {
+                fs::metadata(path)
+                    .map(|meta| Self::from_metadata(&meta))
+                    .unwrap_or_default()
+            })
+            .collect();
+        components.reverse();
+        components
+    }
+
+    /// The metadata of `count` components of which only the last, the target, is known.
+    pub(crate) fn for_target(count: usize, target: Self) -> Vec<Self> {
+        let mut components = vec![Self::default(); count];
+        if let Some(last) = components.last_mut() {
+            *last = target;
+        }
+        components
     }
 }
-/// file entry item
-#[derive(Debug, Default)]
-struct FileEntryItem {
-    size: u16,
-    class_type_indicator: u8,
-    unknown_empty: u8,
-    filesize: u32,
-    mtime: u32,
-    file_attrbute_flags: u16,
-    // usually u8 str,u16 if not ascii,0-terminated
-    entry_name: String,
-    extension_block: ExtensionBlock,
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
 }
-impl FileEntryItem {
-    fn set_size(&mut self) {
-        // size
-        let size_field = 2;
-        let unknown_emp = 1;
-        let class_tp_indicator = 1;
-        let filesize = 4;
-        let mt = 4;
-        let fatr = 2;
-        let entry_name_size = self.return_entry_name_vec().len();
-        let extension_block_size = self.extension_block.size;
-        self.size = (size_field
-            + unknown_emp
-            + class_tp_indicator
-            + filesize
-            + mt
-            + fatr
-            + entry_name_size
-            + extension_block_size as usize) as u16;
-    }
-    /// wide-string to vec
-    fn return_entry_name_wide_vec(&self) -> Vec<u8> {
-        let long_name = &self.entry_name;
-        let data = OsString::from(long_name).encode_wide().collect::<Vec<_>>();
-        let mut data_vec = vec![];
-        for i in data {
-            let mut v = i.to_le_bytes().to_vec();
-            data_vec.append(&mut v);
+
+impl LinkTargetIdList {
+    /// Build the item ID list for a local target such as `C:\dir\app.exe`, or a UNC target
+    /// such as `\\server\share\app.exe`.
+    ///
+    /// The last component is written as a file, unless the path ends with a separator in which
+    /// case it is written as a directory. A drive root (`C:\`) results in only the computer
+    /// and drive items, a share root in only the network, server and share items. Any
+    /// previously set items are replaced.
+    ///
+    /// The file size and times of the target and its parent directories are read from the
+    /// file system where they exist, and left zero otherwise. The primary name of each item
+    /// is the 8.3 short name generated by [`short_name`](crate::short_name), the long name is
+    /// stored in its extension block.
+    pub fn set_linktarget<P: AsRef<Path>>(&mut self, target: P) -> Result<(), MSLinkError> {
+        let target_path = TargetPath::from_path(target.as_ref())?;
+        let is_dir = target.as_ref().to_string_lossy().ends_with(is_separator);
+        let metadata = ItemMetadata::read_components(target.as_ref(), target_path.components.len());
+        self.set_target_path(&target_path, is_dir, &metadata, WindowsVersion::default())
+    }
+
+    /// Build the item ID list for a parsed target, with the size and times in `metadata` for
+    /// each of its components and extension blocks as written by `windows_version`. Fails
+    /// without touching the current items if an item or the whole list would not fit its
+    /// 16-bit size field.
+    pub(crate) fn set_target_path(
+        &mut self,
+        target: &TargetPath,
+        is_dir: bool,
+        metadata: &[ItemMetadata],
+        windows_version: WindowsVersion,
+    ) -> Result<(), MSLinkError> {
+        let mut items = vec![];
+        match &target.root {
+            TargetRoot::Drive(drive) => {
+                items.push(ShellItem::RootFolder(RootFolderItem::my_computer()));
+                items.push(ShellItem::Volume(VolumeItem::new(*drive)));
+            }
+            TargetRoot::Unc { server, share } => {
+                items.push(ShellItem::RootFolder(RootFolderItem::network()));
+                items.push(ShellItem::NetworkLocation(NetworkLocationItem::server(
+                    server,
+                )));
+                items.push(ShellItem::NetworkLocation(NetworkLocationItem::share(
+                    server, share,
+                )));
+            }
         }
-        data_vec.append(&mut 0u16.to_le_bytes().to_vec());
-        data_vec
-    }
-    /// u8 string if ascii,u16 string if not ascii
-    fn return_entry_name_vec(&self) -> Vec<u8> {
-        let long_name = &self.entry_name;
-        let file_name_vec = if !long_name.is_ascii() {
-            let mut m = vec![];
-            let a = OsString::from(long_name).encode_wide().collect::<Vec<_>>();
-            for i in a {
-                m.append(&mut i.to_le_bytes().to_vec());
+        let num_filesystemobjects = target.components.len();
+        for (c, name) in target.components.iter().enumerate() {
+            let is_last = c + 1 == num_filesystemobjects;
+            let metadata = metadata.get(c).cloned().unwrap_or_default();
+            let mut item = match &metadata.short_name {
+                Some(short_name) => {
+                    FileEntryItem::with_short_name(name, short_name, is_dir || !is_last)
+                }
+                None => FileEntryItem::new(name, is_dir || !is_last),
+            };
+            item.set_windows_version(windows_version);
+            if !item.is_dir() {
+                item.set_file_size(metadata.file_size);
             }
+            item.set_times(
+                metadata.creation_time,
+                metadata.access_time,
+                metadata.write_time,
+            );
+            items.push(ShellItem::FileEntry(item));
+        }
+        self.set_items(items)
+    }
 
-            m.append(&mut 0u16.to_le_bytes().to_vec());
-            m
-        } else {
-            // if is ascii
-            let mut m = long_name.as_bytes().to_vec();
-            m.push(0);
-            m
-        };
-        file_name_vec
+    /// Build the item ID list for `path` beneath the virtual folder `folder`, such as
+    /// `Documents\report.docx` beneath [`RootFolder::UserFiles`], or for the folder itself if
+    /// `path` is empty.
+    ///
+    /// The components of `path` are written as file entries, the last one as a file unless
+    /// `path` ends with a separator. Targets on drives and shares are built with
+    /// [`set_linktarget`](Self::set_linktarget) instead. Any previously set items are
+    /// replaced.
+    pub fn set_virtual_target(
+        &mut self,
+        folder: RootFolder,
+        path: &str,
+    ) -> Result<(), MSLinkError> {
+        let is_dir = path.ends_with(is_separator);
+        let components: Vec<&str> = path.split(is_separator).filter(|c| !c.is_empty()).collect();
+        let mut items = vec![ShellItem::RootFolder(RootFolderItem::from(folder))];
+        for (c, name) in components.iter().enumerate() {
+            let is_last = c + 1 == components.len();
+            items.push(ShellItem::FileEntry(FileEntryItem::new(
+                name,
+                is_dir || !is_last,
+            )));
+        }
+        self.set_items(items)
+    }
+
+    /// Replace the items of the list. Fails without touching the current items if an item or
+    /// the whole list would not fit its 16-bit size field.
+    fn set_items(&mut self, items: Vec<ShellItem>) -> Result<(), MSLinkError> {
+        let id_list = items
+            .into_iter()
+            .map(ItemID::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        // sum up itemid.size
+        let mut idlist_size = 2usize;
+        for itemid in &id_list {
+            idlist_size += itemid.size as usize
+        }
+        if idlist_size > u16::MAX as usize {
+            return Err(MSLinkError::too_large(
+                "LinkTargetIDList",
+                idlist_size,
+                u16::MAX as usize,
+            ));
+        }
+        self.size = idlist_size as u16;
+        self.id_list = id_list;
+        self.padding.clear();
+        Ok(())
+    }
+
+    /// The Windows version whose extension blocks the file entry items of the list hold, if
+    /// they hold any of a known version.
+    pub(crate) fn windows_version(&self) -> Option<WindowsVersion> {
+        self.items().find_map(|item| match item {
+            ShellItem::FileEntry(item) => item
+                .extension()
+                .and_then(|block| WindowsVersion::from_extension_version(block.version())),
+            _ => None,
+        })
+    }
+
+    /// The items of the list, as stored.
+    pub fn item_ids(&self) -> &[ItemID] {
+        &self.id_list
+    }
+
+    /// The items of the list as [`ShellItem`]s, from the root of the shell namespace down to
+    /// the target.
+    pub fn items(&self) -> impl Iterator<Item = ShellItem> + '_ {
+        self.id_list.iter().map(ShellItem::from)
+    }
+
+    /// Append an item to the list. Fails if the item or the list would not fit its 16-bit
+    /// size field.
+    pub fn push(&mut self, item: ShellItem) -> Result<(), MSLinkError> {
+        let item = ItemID::try_from(item)?;
+        let size = self.size.max(2) as usize + item.size as usize;
+        if size > u16::MAX as usize {
+            return Err(MSLinkError::too_large(
+                "LinkTargetIDList",
+                size,
+                u16::MAX as usize,
+            ));
+        }
+        self.size = size as u16;
+        self.id_list.push(item);
+        Ok(())
+    }
+
+    /// Check that every ItemIDSize matches its item and that IDListSize is the sum of the
+    /// items, the TerminalID and any padding.
+    pub(crate) fn check_sizes(&self) -> Result<(), MSLinkError> {
+        let mut idlist_size = 2 + self.padding.len();
+        for (i, item) in self.id_list.iter().enumerate() {
+            let item_size = item.data.len() + 2;
+            if item_size > u16::MAX as usize {
+                return Err(MSLinkError::too_large(
+                    &format!("ItemID {}", i),
+                    item_size,
+                    u16::MAX as usize,
+                ));
+            }
+            if item_size != item.size as usize {
+                return Err(MSLinkError::SizeMismatch {
+                    structure: format!("ItemID {}", i),
+                    size: item_size,
+                    expected: item.size as usize,
+                });
+            }
+            idlist_size += item_size;
+        }
+        if idlist_size != self.size as usize {
+            return Err(MSLinkError::SizeMismatch {
+                structure: "LinkTargetIDList".to_owned(),
+                size: idlist_size,
+                expected: self.size as usize,
+            });
+        }
+        Ok(())
     }
 }
-impl Into<Vec<u8>> for FileEntryItem {
-    /// into vec add field first_offset
-    fn into(self) -> Vec<u8> {
-        let len = self.size;
-        let mut data = vec![0u8; 14];
-        // size
-        LE::write_u16(&mut data[0..2], self.size);
-        // class_type_indicator 1
-        *(data.get_mut(2).unwrap()) = self.class_type_indicator;
-        // Unknown (Empty value) 1
-        *(data.get_mut(3).unwrap()) = self.unknown_empty;
-        // filesize
-        LE::write_u32(&mut data[4..8], self.filesize);
-        // mtime
-        LE::write_u32(&mut data[8..12], self.mtime);
-        // file attr
-        LE::write_u16(&mut data[12..14], self.file_attrbute_flags);
-        // entry name vec
-        data.append(&mut self.return_entry_name_vec());
-        // extension block vec except fisrst offsert
-        let mut exblock = self.extension_block.return_vec_except_1stoffset();
-        data.append(&mut exblock);
-        // wide string
-        data.append(&mut self.return_entry_name_wide_vec());
-        // 1st offset
-        data.append(&mut self.extension_block.first_offset.to_le_bytes().to_vec());
-        assert_eq!(len, data.len() as u16);
-        data
+
+impl fmt::Display for LinkTargetIdList {
+    /// Format the list as Explorer shows the location of the target, e.g.
+    /// `This PC > C:\ > Program Files > App > app.exe`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, item) in self.items().enumerate() {
+            if i > 0 {
+                write!(f, " > ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
     }
 }
-/// only support version 8,10
-#[derive(Debug, Default)]
-struct ExtensionBlock {
-    size: u16,
-    version: u16,
-    signature: u32,
-    ctime: u32,
-    atime: u32,
-    unknown_ver_id: u16,
-    unknown_emp: u32,
-    first_offset: u16,
+
+impl TryFrom<&[u8]> for LinkTargetIdList {
+    type Error = MSLinkError;
+
+    /// Read a LinkTargetIDList, starting at its IDListSize field.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 2 {
+            return Err(MSLinkError::truncated("LinkTargetIDList"));
+        }
+        let size = LE::read_u16(data);
+        let mut id_list_data = data
+            .get(2..2 + size as usize)
+            .ok_or_else(|| MSLinkError::truncated("LinkTargetIDList"))?;
+        let mut id_list = vec![];
+        // ItemIDs follow the IDListSize field
+        let mut offset = 2;
+        loop {
+            if id_list_data.len() < 2 {
+                return Err(MSLinkError::truncated("LinkTargetIDList"));
+            }
+            let item_size = LE::read_u16(id_list_data) as usize;
+            // TerminalID
+            if item_size == 0 {
+                id_list_data = &id_list_data[2..];
+                break;
+            }
+            let item = id_list_data
+                .get(..item_size)
+                .filter(|item| item.len() >= 2)
+                .ok_or_else(|| {
+                    MSLinkError::truncated("ItemID")
+                        .within(&format!("ItemID[{}]", id_list.len()), offset)
+                })?;
+            id_list.push(ItemID::from(item));
+            id_list_data = &id_list_data[item_size..];
+            offset += item_size;
+        }
+        Ok(Self {
+            size,
+            id_list,
+            padding: id_list_data.to_vec(),
+        })
+    }
 }
-impl ExtensionBlock {
-    fn set_size(&mut self, wide_vec_len: u16) {
-        self.size = size_of::<ExtensionBlock>() as u16 + wide_vec_len;
-    }
-    fn return_vec_except_1stoffset(&self) -> Vec<u8> {
-        let mut data = vec![0u8; 22];
-        // need offset
-        LE::write_u16(&mut data[0..2], self.size);
-        LE::write_u16(&mut data[2..4], self.version);
-        LE::write_u32(&mut data[4..8], self.signature);
-        LE::write_u32(&mut data[8..12], self.ctime);
-        LE::write_u32(&mut data[12..16], self.atime);
-        LE::write_u16(&mut data[16..18], self.unknown_ver_id);
-        LE::write_u32(&mut data[18..22], self.unknown_emp);
+
+impl From<LinkTargetIdList> for Vec<u8> {
+    fn from(val: LinkTargetIdList) -> Self {
+        let mut data = vec![0, 0];
+        LE::write_u16(&mut data[0..2], val.size);
+        for id in val.id_list {
+            let mut other_data = id.into();
+            data.append(&mut other_data);
+        }
+        // add terminal id 0000
+        data.append(&mut 0u16.to_le_bytes().to_vec());
+        data.extend_from_slice(&val.padding);
         data
     }
 }
-
 /// The stored IDList structure specifies the format of a persisted item ID list.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ItemID {
     /// A 16-bit, unsigned integer that specifies the size, in bytes, of the ItemID structure,
     /// including the ItemIDSize field.
@@ -270,12 +486,29 @@ pub struct ItemID {
     data: Vec<u8>,
 }
 
-impl Default for ItemID {
-    fn default() -> Self {
-        Self {
-            size: 0,
-            data: Vec::new(),
+impl ItemID {
+    /// Create an ItemID holding `data`, which follows ItemIDSize. Fails if the item doesn't
+    /// fit the 16-bit ItemIDSize.
+    pub fn new(data: Vec<u8>) -> Result<Self, MSLinkError> {
+        let size = data.len() + 2;
+        if size > u16::MAX as usize {
+            return Err(MSLinkError::too_large("ItemID", size, u16::MAX as usize));
         }
+        Ok(Self {
+            size: size as u16,
+            data,
+        })
+    }
+
+    /// The size of the item, including the ItemIDSize field.
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// The item data following ItemIDSize, starting with the class type indicator of most
+    /// shell items.
+    pub fn data(&self) -> &[u8] {
+        &self.data
     }
 }
 
@@ -291,13 +524,13 @@ impl From<&[u8]> for ItemID {
     }
 }
 
-impl Into<Vec<u8>> for ItemID {
-    fn into(self) -> Vec<u8> {
+impl From<ItemID> for Vec<u8> {
+    fn from(val: ItemID) -> Self {
         let mut data = vec![0, 0];
-        assert_eq!(self.data.len() as u16 + 2, self.size);
+        assert_eq!(val.data.len() as u16 + 2, val.size);
 
-        LE::write_u16(&mut data, self.size);
-        let mut other_data = self.data;
+        LE::write_u16(&mut data, val.size);
+        let mut other_data = val.data;
         data.append(&mut other_data);
 
         data
@@ -308,7 +541,7 @@ impl Into<Vec<u8>> for ItemID {
 fn tests() {
     let s = r"D:\文档_~1\anki\ANKI__~1\ANKI_S~1.36\ANKI_S~1.EXE";
     let z = Path::new(s);
-    let a = OsString::from("文档_~1").encode_wide().collect::<Vec<_>>();
+    let a = "文档_~1".encode_utf16().collect::<Vec<_>>();
     for i in a {
         println!("{:02x}{:02x}", i.to_le_bytes()[0], i.to_le_bytes()[1]);
     }
@@ -322,3 +555,226 @@ fn test_writele() {
     // fail
     LE::write_u16(&mut v, 10u16);
 }
+
+#[test]
+fn test_target_path_shapes() {
+    let root = TargetPath::parse(r"c:\").unwrap();
+    assert_eq!(root.root, TargetRoot::Drive('C'));
+    assert!(root.components.is_empty());
+    assert_eq!(root.parent(), None);
+    assert_eq!(root.file_name(), None);
+    assert_eq!(TargetPath::parse("D:").unwrap().components.len(), 0);
+
+    let in_root = TargetPath::parse(r"C:\app.exe").unwrap();
+    assert_eq!(in_root.parent().as_deref(), Some(r"C:\"));
+    assert_eq!(in_root.file_name(), Some("app.exe"));
+
+    let trailing = TargetPath::parse(r"C:\Tools\\bin\").unwrap();
+    assert_eq!(trailing.components, vec!["Tools", "bin"]);
+    assert_eq!(trailing.parent().as_deref(), Some(r"C:\Tools"));
+
+    assert!(TargetPath::parse("app.exe").is_err());
+    assert!(TargetPath::parse("C:app.exe").is_err());
+    assert!(TargetPath::parse("").is_err());
+}
+
+#[test]
+fn test_linktarget_drive_root() {
+    for target in &[r"D:\", "D:", "D:/"] {
+        let mut idlist = LinkTargetIdList::default();
+        idlist.set_linktarget(target).unwrap();
+        assert_eq!(idlist.id_list.len(), 2);
+        assert_eq!(idlist.id_list[1].data[1], b'D');
+        assert_eq!(idlist.size, 2 + 20 + 25);
+    }
+}
+
+#[test]
+fn test_linktarget_file_in_root() {
+    let mut idlist = LinkTargetIdList::default();
+    idlist.set_linktarget(r"C:\app.exe").unwrap();
+    assert_eq!(idlist.id_list.len(), 3);
+    // class type indicator of a file entry
+    assert_eq!(idlist.id_list[2].data[0], 0x32);
+    let data: Vec<u8> = idlist.clone().into();
+    assert_eq!(data.len(), idlist.size as usize + 2);
+}
+
+#[test]
+fn test_linktarget_trailing_separator() {
+    let mut idlist = LinkTargetIdList::default();
+    idlist.set_linktarget(r"C:\Tools\").unwrap();
+    assert_eq!(idlist.id_list.len(), 3);
+    // class type indicator of a directory entry
+    assert_eq!(idlist.id_list[2].data[0], 0x31);
+
+    // setting the target again replaces the previous items
+    idlist.set_linktarget(r"C:\Tools\\bin\app.exe").unwrap();
+    assert_eq!(idlist.id_list.len(), 5);
+    assert_eq!(idlist.id_list[3].data[0], 0x31);
+    assert_eq!(idlist.id_list[4].data[0], 0x32);
+}
+
+#[test]
+fn test_target_path_unc() {
+    let unc = TargetPath::parse(r"\\fileserver\share\tools\tool.exe").unwrap();
+    assert_eq!(
+        unc.root,
+        TargetRoot::Unc {
+            server: "fileserver".to_owned(),
+            share: "share".to_owned()
+        }
+    );
+    assert_eq!(unc.suffix(), r"tools\tool.exe");
+    assert_eq!(unc.parent().as_deref(), Some(r"\\fileserver\share\tools"));
+
+    let share_root = TargetPath::parse(r"\\fileserver\share\").unwrap();
+    assert!(share_root.components.is_empty());
+    assert_eq!(share_root.root(), r"\\fileserver\share\");
+
+    assert!(TargetPath::parse(r"\\fileserver").is_err());
+    assert!(TargetPath::parse(r"\\fileserver\").is_err());
+}
+
+#[test]
+fn test_linktarget_unc() {
+    let mut idlist = LinkTargetIdList::default();
+    idlist
+        .set_linktarget(r"\\fileserver\share\tool.exe")
+        .unwrap();
+    assert_eq!(idlist.id_list.len(), 4);
+    assert_eq!(
+        idlist.id_list[0].data,
+        Vec::<u8>::from(RootFolderItem::network())
+    );
+    // server item: class type, unknown, flags, location
+    let server = &idlist.id_list[1].data;
+    assert_eq!(server[0], 0xc2);
+    assert!(server[3..].starts_with(b"\\\\fileserver\0"));
+    let share = &idlist.id_list[2].data;
+    assert_eq!(share[0], 0xc3);
+    assert!(share[3..].starts_with(b"\\\\fileserver\\share\0"));
+    assert_eq!(idlist.id_list[3].data[0], 0x32);
+
+    let size: u16 = idlist.id_list.iter().map(|id| id.size).sum();
+    assert_eq!(idlist.size, size + 2);
+}
+
+#[test]
+fn test_target_path_long_path_prefix() {
+    let local = TargetPath::parse(r"\\?\C:\Tools\app.exe").unwrap();
+    assert_eq!(local, TargetPath::parse(r"C:\Tools\app.exe").unwrap());
+    assert_eq!(local.path(), r"C:\Tools\app.exe");
+
+    let unc = TargetPath::parse(r"\\?\unc\server\share\app.exe").unwrap();
+    assert_eq!(unc, TargetPath::parse(r"\\server\share\app.exe").unwrap());
+    assert_eq!(unc.path(), r"\\server\share\app.exe");
+
+    assert!(TargetPath::parse(r"\\?\relative").is_err());
+}
+
+#[test]
+fn test_linktarget_long_path() {
+    // 20 components of 100 characters, far beyond MAX_PATH
+    let component = "a".repeat(100);
+    let target = format!(r"\\?\C:\{}", vec![component.as_str(); 20].join("\\"));
+    let mut idlist = LinkTargetIdList::default();
+    idlist.set_linktarget(&target).unwrap();
+    assert_eq!(idlist.id_list.len(), 22);
+    let data: Vec<u8> = idlist.clone().into();
+    assert_eq!(data.len(), idlist.size as usize + 2);
+    let parsed = LinkTargetIdList::try_from(data.as_slice()).unwrap();
+    assert_eq!(parsed.id_list.len(), 22);
+}
+
+#[test]
+fn test_linktarget_too_large() {
+    let mut idlist = LinkTargetIdList::default();
+    idlist.set_linktarget(r"C:\app.exe").unwrap();
+
+    // a single ItemID over 64 KiB
+    let target = format!(r"C:\{}", "a".repeat(40000));
+    assert!(idlist.set_linktarget(&target).is_err());
+    // a whole IDList over 64 KiB
+    let component = "a".repeat(200);
+    let target = format!(r"C:\{}", vec![component.as_str(); 200].join("\\"));
+    assert!(idlist.set_linktarget(&target).is_err());
+    // a failed call keeps the previous target
+    assert_eq!(idlist.id_list.len(), 3);
+}
+
+#[test]
+fn test_virtual_target() {
+    let mut idlist = LinkTargetIdList::default();
+    for folder in RootFolder::ALL {
+        idlist.set_virtual_target(folder, "").unwrap();
+        assert_eq!(idlist.id_list.len(), 1);
+        assert_eq!(idlist.to_string(), folder.name());
+        assert_eq!(RootFolder::from_clsid(folder.clsid()), Some(folder));
+    }
+
+    idlist
+        .set_virtual_target(RootFolder::UserFiles, r"Documents\report.docx")
+        .unwrap();
+    let data: Vec<u8> = idlist.into();
+    let parsed = LinkTargetIdList::try_from(data.as_slice()).unwrap();
+    assert_eq!(parsed.to_string(), "User files > Documents > report.docx");
+    let items: Vec<_> = parsed.items().collect();
+    assert!(matches!(&items[0], ShellItem::RootFolder(item) if item.sort_index() == 0x44));
+    assert!(matches!(&items[1], ShellItem::FileEntry(item) if item.is_dir()));
+    assert!(matches!(&items[2], ShellItem::FileEntry(item) if !item.is_dir()));
+}
+
+#[test]
+fn test_compressed_and_network_targets() {
+    use crate::{CompressedFolderItem, NetworkLocationType};
+
+    let mut idlist = LinkTargetIdList::default();
+    idlist
+        .set_virtual_target(RootFolder::UserFiles, r"Downloads\archive.zip")
+        .unwrap();
+    for path in &["docs", "docs/readme.txt"] {
+        let item = CompressedFolderItem::new(path, 128_657_248_395_000_000);
+        idlist.push(ShellItem::CompressedFolder(item)).unwrap();
+    }
+    let data: Vec<u8> = idlist.into();
+    let parsed = LinkTargetIdList::try_from(data.as_slice()).unwrap();
+    assert_eq!(
+        parsed.to_string(),
+        "User files > Downloads > archive.zip > docs > readme.txt"
+    );
+    match parsed.items().last().unwrap() {
+        ShellItem::CompressedFolder(item) => {
+            assert_eq!(item.path(), "docs/readme.txt");
+            assert_eq!(item.modification_time(), "09/12/2008  20:27");
+        }
+        item => panic!("not a compressed folder item: {:?}", item),
+    }
+    assert_eq!(Vec::<u8>::from(parsed), data);
+
+    let mut idlist = LinkTargetIdList::default();
+    idlist.set_virtual_target(RootFolder::Network, "").unwrap();
+    idlist
+        .push(ShellItem::NetworkLocation(NetworkLocationItem::webdav(
+            "files.example.com",
+            true,
+        )))
+        .unwrap();
+    idlist
+        .push(ShellItem::FileEntry(FileEntryItem::new("Reports", true)))
+        .unwrap();
+    let data: Vec<u8> = idlist.into();
+    let parsed = LinkTargetIdList::try_from(data.as_slice()).unwrap();
+    assert_eq!(
+        parsed.to_string(),
+        r"Network > \\files.example.com@SSL\DavWWWRoot > Reports"
+    );
+    match &parsed.items().collect::<Vec<_>>()[1] {
+        ShellItem::NetworkLocation(item) => {
+            assert_eq!(item.location_type(), NetworkLocationType::Share);
+            assert!(item.is_webdav());
+            assert_eq!(item.description(), Some("Web Client Network"));
+        }
+        item => panic!("not a network location item: {:?}", item),
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{stringdata, ExtraData, Guid, MSLinkError};
+use byteorder::{ByteOrder, LE};
+use std::convert::TryFrom;
+
+/// The Version field of a serialized property storage, `1SPS`.
+const STORAGE_VERSION: u32 = 0x53505331;
+
+/// The format ID of storages whose properties are named by strings instead of IDs.
+const STRING_NAMED_FORMAT_ID: Guid = Guid::from_fields(
+    0xD5CDD505,
+    0x2E9C,
+    0x101B,
+    [0x93, 0x97, 0x08, 0x00, 0x2B, 0x2C, 0xF9, 0xAE],
+);
+
+/// The format ID of the System.AppUserModel properties.
+pub(crate) const APP_USER_MODEL_FORMAT_ID: Guid = Guid::from_fields(
+    0x9F4C2855,
+    0x9F79,
+    0x4B39,
+    [0xA8, 0xD0, 0xE1, 0xD4, 0x2D, 0xE1, 0xD5, 0xF3],
+);
+
+/// The property ID of System.AppUserModel.ID, the AUMID of an app.
+pub(crate) const APP_USER_MODEL_ID: u32 = 5;
+
+const VT_BOOL: u16 = 0x000b;
+const VT_UI4: u16 = 0x0013;
+const VT_LPWSTR: u16 = 0x001f;
+const VT_CLSID: u16 = 0x0048;
+
+/// The value of a property in a [`PropertyStore`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PropertyValue {
+    /// A VT_LPWSTR string.
+    String(String),
+    /// A VT_UI4 unsigned integer.
+    UInt32(u32),
+    /// A VT_BOOL.
+    Bool(bool),
+    /// A VT_CLSID.
+    Guid(Guid),
+    /// A value of any other type, with the data following its type and padding fields.
+    Other {
+        /// The VARTYPE of the value.
+        value_type: u16,
+        /// The value as read.
+        data: Vec<u8>,
+    },
+}
+
+impl PropertyValue {
+    /// Read a TypedPropertyValue, which `data` holds exactly.
+    fn parse(data: &[u8]) -> Result<Self, MSLinkError> {
+        let truncated = || MSLinkError::truncated("TypedPropertyValue");
+        if data.len() < 4 {
+            return Err(truncated());
+        }
+        let value_type = LE::read_u16(data);
+        let value = &data[4..];
+        Ok(match value_type {
+            VT_LPWSTR => {
+                let chars = value.get(..4).map(LE::read_u32).ok_or_else(truncated)? as usize;
+                let string = value.get(4..4 + chars * 2).ok_or_else(truncated)?;
+                Self::String(stringdata::parse_unicode_z(string, "TypedPropertyValue")?)
+            }
+            VT_UI4 => Self::UInt32(value.get(..4).map(LE::read_u32).ok_or_else(truncated)?),
+            VT_BOOL => Self::Bool(value.get(..2).map(LE::read_u16).ok_or_else(truncated)? != 0),
+            VT_CLSID => Self::Guid(Guid::read(value).ok_or_else(truncated)?),
+            _ => Self::Other {
+                value_type,
+                data: value.to_vec(),
+            },
+        })
+    }
+
+    /// The TypedPropertyValue, padded to a multiple of 4 bytes.
+    fn to_bytes(&self) -> Vec<u8> {
+        let (value_type, mut value) = match self {
+            Self::String(string) => {
+                let string = stringdata::to_unicode_z(string);
+                let mut value = ((string.len() / 2) as u32).to_le_bytes().to_vec();
+                value.extend_from_slice(&string);
+                (VT_LPWSTR, value)
+            }
+            Self::UInt32(value) => (VT_UI4, value.to_le_bytes().to_vec()),
+            Self::Bool(value) => {
+                let value: u16 = if *value { 0xffff } else { 0 };
+                (VT_BOOL, value.to_le_bytes().to_vec())
+            }
+            Self::Guid(guid) => (VT_CLSID, guid.to_bytes().to_vec()),
+            Self::Other { value_type, data } => (*value_type, data.clone()),
+        };
+        let mut data = vec![0u8; 4];
+        LE::write_u16(&mut data, value_type);
+        data.append(&mut value);
+        data.resize(data.len().div_ceil(4) * 4, 0);
+        data
+    }
+}
+
+/// How a property is named within its storage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PropertyName {
+    Id(u32),
+    String(String),
+}
+
+/// A serialized property storage, holding the properties of one format ID.
+#[derive(Clone, Debug)]
+struct PropertyStorage {
+    format_id: Guid,
+    values: Vec<(PropertyName, PropertyValue)>,
+}
+
+/// A serialized property store ([MS-PROPSTORE]), as held by the PropertyStoreDataBlock of a
+/// link and by some shell items.
+///
+/// Properties are identified by the format ID and property ID of their property key, such
+/// as System.AppUserModel.ID. Properties named by strings are kept but can't be looked up.
+#[derive(Clone, Debug, Default)]
+pub struct PropertyStore {
+    storages: Vec<PropertyStorage>,
+}
+
+impl PropertyStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the property `id` of the format `format_id`, if set.
+    pub fn get(&self, format_id: Guid, id: u32) -> Option<&PropertyValue> {
+        self.storages
+            .iter()
+            .filter(|storage| storage.format_id == format_id)
+            .flat_map(|storage| &storage.values)
+            .find(|(name, _)| name == &PropertyName::Id(id))
+            .map(|(_, value)| value)
+    }
+
+    /// Set the property `id` of the format `format_id`, replacing any previous value.
+    pub fn set(&mut self, format_id: Guid, id: u32, value: PropertyValue) {
+        let name = PropertyName::Id(id);
+        for storage in &mut self.storages {
+            if storage.format_id == format_id {
+                if let Some(entry) = storage.values.iter_mut().find(|(n, _)| n == &name) {
+                    entry.1 = value;
+                    return;
+                }
+            }
+        }
+        match self
+            .storages
+            .iter_mut()
+            .find(|storage| storage.format_id == format_id)
+        {
+            Some(storage) => storage.values.push((name, value)),
+            None => self.storages.push(PropertyStorage {
+                format_id,
+                values: vec![(name, value)],
+            }),
+        }
+    }
+
+    /// The AUMID of the app the link belongs to, System.AppUserModel.ID, if set.
+    pub fn app_user_model_id(&self) -> Option<&str> {
+        match self.get(APP_USER_MODEL_FORMAT_ID, APP_USER_MODEL_ID) {
+            Some(PropertyValue::String(aumid)) => Some(aumid),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for PropertyStore {
+    type Error = MSLinkError;
+
+    /// Read a store up to its terminating zero storage size, such as the data of a
+    /// PropertyStoreDataBlock.
+    fn try_from(mut data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = || MSLinkError::truncated("PropertyStore");
+        let mut storages = vec![];
+        loop {
+            let size = data.get(..4).map(LE::read_u32).ok_or_else(truncated)? as usize;
+            if size == 0 {
+                return Ok(Self { storages });
+            }
+            let storage = data
+                .get(..size)
+                .filter(|_| size >= 24)
+                .ok_or_else(truncated)?;
+            if LE::read_u32(&storage[4..]) != STORAGE_VERSION {
+                return Err(MSLinkError::InvalidValue {
+                    field: "PropertyStore Version".to_owned(),
+                    value: LE::read_u32(&storage[4..]),
+                });
+            }
+            let format_id = Guid::read(&storage[8..]).unwrap();
+            let mut values = vec![];
+            let mut rest = &storage[24..];
+            loop {
+                let value_size = rest.get(..4).map(LE::read_u32).ok_or_else(truncated)? as usize;
+                if value_size == 0 {
+                    break;
+                }
+                let value = rest
+                    .get(..value_size)
+                    .filter(|_| value_size >= 9)
+                    .ok_or_else(truncated)?;
+                let (name, typed_value) = if format_id == STRING_NAMED_FORMAT_ID {
+                    let name_size = LE::read_u32(&value[4..]) as usize;
+                    let name = value.get(9..9 + name_size).ok_or_else(truncated)?;
+                    let name = stringdata::parse_unicode_z(name, "PropertyStore")?;
+                    (PropertyName::String(name), &value[9 + name_size..])
+                } else {
+                    (PropertyName::Id(LE::read_u32(&value[4..])), &value[9..])
+                };
+                values.push((name, PropertyValue::parse(typed_value)?));
+                rest = &rest[value_size..];
+            }
+            storages.push(PropertyStorage { format_id, values });
+            data = &data[size..];
+        }
+    }
+}
+
+impl From<&PropertyStore> for Vec<u8> {
+    fn from(val: &PropertyStore) -> Self {
+        let mut data = vec![];
+        for storage in &val.storages {
+            let mut storage_data = vec![0u8; 8];
+            LE::write_u32(&mut storage_data[4..], STORAGE_VERSION);
+            storage_data.extend_from_slice(&storage.format_id.to_bytes());
+            for (name, value) in &storage.values {
+                let mut value_data = vec![0u8; 4];
+                match name {
+                    PropertyName::Id(id) => {
+                        value_data.extend_from_slice(&id.to_le_bytes());
+                        // Reserved
+                        value_data.push(0);
+                    }
+                    PropertyName::String(name) => {
+                        let name = stringdata::to_unicode_z(name);
+                        value_data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                        value_data.push(0);
+                        value_data.extend_from_slice(&name);
+                    }
+                }
+                value_data.append(&mut value.to_bytes());
+                let value_size = value_data.len() as u32;
+                LE::write_u32(&mut value_data, value_size);
+                storage_data.append(&mut value_data);
+            }
+            storage_data.extend_from_slice(&[0; 4]);
+            let storage_size = storage_data.len() as u32;
+            LE::write_u32(&mut storage_data, storage_size);
+            data.append(&mut storage_data);
+        }
+        data.extend_from_slice(&[0; 4]);
+        data
+    }
+}
+
+impl From<&PropertyStore> for ExtraData {
+    /// A PropertyStoreDataBlock holding the store.
+    fn from(val: &PropertyStore) -> Self {
+        ExtraData::PropertyStoreProps(val.into())
+    }
+}
+
+#[test]
+fn test_property_store() {
+    let mut store = PropertyStore::new();
+    store.set(
+        APP_USER_MODEL_FORMAT_ID,
+        APP_USER_MODEL_ID,
+        PropertyValue::String("Contoso.App_8wekyb3d8bbwe!App".to_owned()),
+    );
+    store.set(APP_USER_MODEL_FORMAT_ID, 9, PropertyValue::Bool(true));
+    // System.Title
+    let summary_information = Guid::from_fields(
+        0xF29F85E0,
+        0x4FF9,
+        0x1068,
+        [0xAB, 0x91, 0x08, 0x00, 0x2B, 0x27, 0xB3, 0xD9],
+    );
+    store.set(
+        summary_information,
+        2,
+        PropertyValue::String("Calculator".to_owned()),
+    );
+    let data: Vec<u8> = (&store).into();
+    // one storage per format ID, each ending with a zero value size, then the zero storage
+    // size ending the store
+    assert_eq!(LE::read_u32(&data[4..]), STORAGE_VERSION);
+    assert_eq!(&data[data.len() - 4..], [0; 4]);
+
+    let parsed = PropertyStore::try_from(&data[..]).unwrap();
+    assert_eq!(
+        parsed.app_user_model_id(),
+        Some("Contoso.App_8wekyb3d8bbwe!App")
+    );
+    assert_eq!(
+        parsed.get(APP_USER_MODEL_FORMAT_ID, 9),
+        Some(&PropertyValue::Bool(true))
+    );
+    assert_eq!(
+        parsed.get(summary_information, 2),
+        Some(&PropertyValue::String("Calculator".to_owned()))
+    );
+    assert_eq!(Vec::<u8>::from(&parsed), data);
+
+    // string-named properties and unknown types are kept
+    let mut named = vec![0u8; 24];
+    LE::write_u32(&mut named[4..], STORAGE_VERSION);
+    named[8..24].copy_from_slice(&STRING_NAMED_FORMAT_ID.to_bytes());
+    let name = stringdata::to_unicode_z("Key");
+    let mut value = vec![0u8; 9];
+    LE::write_u32(&mut value[4..], name.len() as u32);
+    value.extend_from_slice(&name);
+    value.extend_from_slice(&[0x14, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+    let size = value.len() as u32;
+    LE::write_u32(&mut value, size);
+    named.extend_from_slice(&value);
+    named.extend_from_slice(&[0; 4]);
+    let size = named.len() as u32;
+    LE::write_u32(&mut named, size);
+    named.extend_from_slice(&[0; 4]);
+    let parsed = PropertyStore::try_from(&named[..]).unwrap();
+    assert_eq!(Vec::<u8>::from(&parsed), named);
+
+    assert!(PropertyStore::try_from(&data[..data.len() - 8]).is_err());
+}
@@ -0,0 +1,62 @@
+use crate::MSLinkError;
+
+/// How [`ShellLink::from_bytes_with`](crate::ShellLink::from_bytes_with) deals with links that
+/// don't follow the specification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReadMode {
+    /// Fail on the first violation of the specification, including a missing TerminalBlock,
+    /// data after it and LinkFlags that don't match the structures present.
+    Strict,
+    /// Recover as much of the link as possible and report every problem as a warning. Only a
+    /// damaged ShellLinkHeader fails reading.
+    ///
+    /// A structure that can't be read is left out and its LinkFlags bit cleared. Reading goes
+    /// on behind it if its size field is intact, otherwise the rest of the link is left out.
+    Lenient,
+}
+
+/// Problems found while reading a link, handled according to the [`ReadMode`].
+///
+/// Without a mode, as used by [`ShellLink::from_bytes`](crate::ShellLink::from_bytes),
+/// damaged structures fail reading and other violations are ignored.
+pub(crate) struct Issues {
+    mode: Option<ReadMode>,
+    warnings: Vec<MSLinkError>,
+}
+
+impl Issues {
+    pub(crate) fn new(mode: Option<ReadMode>) -> Self {
+        Self {
+            mode,
+            warnings: vec![],
+        }
+    }
+
+    /// A structure can't be read. Returns the error unless reading leniently.
+    pub(crate) fn damaged(&mut self, error: MSLinkError) -> Result<(), MSLinkError> {
+        match self.mode {
+            Some(ReadMode::Lenient) => {
+                self.warnings.push(error);
+                Ok(())
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// A structure can be read, but violates the specification. Returns the error only when
+    /// reading strictly.
+    pub(crate) fn violation(&mut self, error: MSLinkError) -> Result<(), MSLinkError> {
+        match self.mode {
+            Some(ReadMode::Strict) => Err(error),
+            Some(ReadMode::Lenient) => {
+                self.warnings.push(error);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn into_warnings(self) -> Vec<MSLinkError> {
+        self.warnings
+    }
+}
@@ -0,0 +1,329 @@
+use crate::MSLinkError;
+use log::debug;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes temporary files of concurrent saves within this process.
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Options for saving a shell link with [`ShellLink::save`](crate::ShellLink::save).
+///
+/// The defaults write to a temporary file next to the link and rename it into place, replace
+/// an existing link, keep no backup and leave flushing to the operating system.
+#[derive(Clone, Debug)]
+pub struct SaveOptions {
+    atomic: bool,
+    overwrite: bool,
+    backup_suffix: Option<OsString>,
+    sync: SyncPolicy,
+}
+
+/// When saved data is forced to disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SyncPolicy {
+    /// Leave flushing to the operating system.
+    Never,
+    /// Sync the link file before it is renamed into place.
+    File,
+    /// Sync the link file, and on Unix also the directory holding it, so the rename itself
+    /// survives a crash.
+    FileAndDirectory,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            atomic: true,
+            overwrite: true,
+            backup_suffix: None,
+            sync: SyncPolicy::Never,
+        }
+    }
+}
+
+impl SaveOptions {
+    /// Whether the link is written to a temporary file and renamed over the target, so readers
+    /// only ever see the old or the new link
+    pub fn atomic(&self) -> bool {
+        self.atomic
+    }
+
+    /// Set whether the link is written to a temporary file and renamed over the target
+    pub fn set_atomic(&mut self, atomic: bool) {
+        self.atomic = atomic;
+    }
+
+    /// Whether an existing file at the target path is replaced
+    pub fn overwrite(&self) -> bool {
+        self.overwrite
+    }
+
+    /// Set whether an existing file at the target path is replaced. If not, saving fails with
+    /// an `AlreadyExists` io error.
+    ///
+    /// An atomic save that doesn't overwrite installs the link with a hard link. On file
+    /// systems without hard links, such as FAT, the link is written in place instead, so
+    /// readers may see it partly written; a failed write removes it again.
+    pub fn set_overwrite(&mut self, overwrite: bool) {
+        self.overwrite = overwrite;
+    }
+
+    /// The suffix appended to the file name of the backup of an existing link, if any
+    pub fn backup_suffix(&self) -> Option<&OsString> {
+        self.backup_suffix.as_ref()
+    }
+
+    /// Keep a copy of an existing link, named by appending `suffix` (e.g. `.bak`) to its file
+    /// name, before replacing it
+    pub fn set_backup_suffix<S: Into<OsString>>(&mut self, suffix: Option<S>) {
+        self.backup_suffix = suffix.map(Into::into);
+    }
+
+    /// When saved data is forced to disk
+    pub fn sync(&self) -> SyncPolicy {
+        self.sync
+    }
+
+    /// Set when saved data is forced to disk
+    pub fn set_sync(&mut self, sync: SyncPolicy) {
+        self.sync = sync;
+    }
+}
+
+/// Append `suffix` to the file name of `path`.
+fn with_suffix<S: AsRef<OsStr>>(path: &Path, suffix: S) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Save already serialized link data according to `options`.
+pub(crate) fn save(data: &[u8], path: &Path, options: &SaveOptions) -> Result<(), MSLinkError> {
+    // only a link that is about to be replaced is backed up
+    if let (Some(suffix), true) = (&options.backup_suffix, options.overwrite) {
+        match fs::read(path) {
+            Ok(old) => {
+                let permissions = fs::metadata(path)?.permissions();
+                install(
+                    &old,
+                    &with_suffix(path, suffix),
+                    true,
+                    Some(permissions),
+                    options.sync,
+                )?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if !options.atomic {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .create_new(!options.overwrite)
+            .open(path)?;
+        file.write_all(data)?;
+        if options.sync != SyncPolicy::Never {
+            file.sync_all()?;
+        }
+        return Ok(());
+    }
+
+    let permissions = match options.overwrite {
+        true => fs::metadata(path).ok().map(|meta| meta.permissions()),
+        false => None,
+    };
+    install(data, path, options.overwrite, permissions, options.sync)?;
+    Ok(())
+}
+
+/// Write `data` to a temporary file next to `path` and move it into place, so readers only
+/// ever see the old or the new file. An existing file is only replaced if `overwrite` is set,
+/// in which case its `permissions` are given to the new one.
+fn install(
+    data: &[u8],
+    path: &Path,
+    overwrite: bool,
+    permissions: Option<Permissions>,
+    sync: SyncPolicy,
+) -> io::Result<()> {
+    let tmp = with_suffix(
+        path,
+        format!(
+            ".{}-{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    let result = write_new(&tmp, data, sync)
+        .and_then(|_| match permissions {
+            Some(permissions) => fs::set_permissions(&tmp, permissions),
+            None => Ok(()),
+        })
+        .and_then(|_| match overwrite {
+            true => fs::rename(&tmp, path),
+            false => link_new(&tmp, path, data, sync),
+        });
+    // left behind by a failure or by the hard link, gone after a rename
+    let _ = fs::remove_file(&tmp);
+    result?;
+
+    if sync == SyncPolicy::FileAndDirectory {
+        sync_parent(path)?;
+    }
+    Ok(())
+}
+
+/// Give `tmp` the name `path` without replacing an existing file, which makes a hard link fail
+/// with `AlreadyExists` in one step. File systems without hard links, such as FAT, fall back to
+/// creating `path` exclusively and writing `data` to it, which isn't atomic.
+fn link_new(tmp: &Path, path: &Path, data: &[u8], sync: SyncPolicy) -> io::Result<()> {
+    match fs::hard_link(tmp, path) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+            debug!("Hard linking {} failed: {}", path.display(), e);
+            write_new(path, data, sync)
+        }
+        result => result,
+    }
+}
+
+/// Create `path` and write `data` to it. If writing fails, the file is removed again, so no
+/// partly written file is left behind.
+fn write_new(path: &Path, data: &[u8], sync: SyncPolicy) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    let result = file.write_all(data).and_then(|_| match sync {
+        SyncPolicy::Never => Ok(()),
+        _ => file.sync_all(),
+    });
+    if result.is_err() {
+        drop(file);
+        let _ = fs::remove_file(path);
+    }
+    result
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+/// Directories can't be opened for syncing on Windows, where renames are journaled by NTFS.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mslnk_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_atomic_save_replaces() {
+    let dir = test_dir("atomic_save_replaces");
+    let path = dir.join("app.lnk");
+    fs::write(&path, b"old").unwrap();
+
+    let mut options = SaveOptions::default();
+    options.set_sync(SyncPolicy::FileAndDirectory);
+    save(b"new", &path, &options).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+    // only the link is left behind
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_refuses_overwrite() {
+    let dir = test_dir("save_refuses_overwrite");
+    let path = dir.join("app.lnk");
+    fs::write(&path, b"old").unwrap();
+
+    let mut options = SaveOptions::default();
+    options.set_overwrite(false);
+    assert!(save(b"new", &path, &options).is_err());
+    options.set_atomic(false);
+    assert!(save(b"new", &path, &options).is_err());
+    assert_eq!(fs::read(&path).unwrap(), b"old");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    let other = dir.join("other.lnk");
+    save(b"new", &other, &options).unwrap();
+    assert_eq!(fs::read(&other).unwrap(), b"new");
+    // installed through a hard link, leaving no temporary file behind
+    options.set_atomic(true);
+    let third = dir.join("third.lnk");
+    save(b"new", &third, &options).unwrap();
+    assert_eq!(fs::read(&third).unwrap(), b"new");
+    assert!(save(b"newer", &third, &options).is_err());
+    assert_eq!(fs::read(&third).unwrap(), b"new");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_keeps_backup() {
+    let dir = test_dir("save_keeps_backup");
+    let path = dir.join("app.lnk");
+    let mut options = SaveOptions::default();
+    options.set_backup_suffix(Some(".bak"));
+
+    // nothing to back up yet
+    save(b"old", &path, &options).unwrap();
+    assert!(!dir.join("app.lnk.bak").exists());
+
+    save(b"new", &path, &options).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+    assert_eq!(fs::read(dir.join("app.lnk.bak")).unwrap(), b"old");
+    // the backup is replaced atomically too
+    options.set_sync(SyncPolicy::FileAndDirectory);
+    save(b"newer", &path, &options).unwrap();
+    assert_eq!(fs::read(dir.join("app.lnk.bak")).unwrap(), b"new");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_failed_atomic_save_cleans_up() {
+    let dir = test_dir("failed_atomic_save_cleans_up");
+    // renaming a file over a non-empty directory fails
+    let path = dir.join("app.lnk");
+    fs::create_dir(&path).unwrap();
+    fs::write(path.join("inner"), b"").unwrap();
+
+    assert!(save(b"new", &path, &SaveOptions::default()).is_err());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_save_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = test_dir("save_keeps_permissions");
+    let path = dir.join("app.lnk");
+    fs::write(&path, b"old").unwrap();
+    fs::set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+
+    let mut options = SaveOptions::default();
+    options.set_backup_suffix(Some(".bak"));
+    save(b"new", &path, &options).unwrap();
+    for path in &[path.clone(), dir.join("app.lnk.bak")] {
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,1795 @@
+use crate::extensionblock::{self, ExtensionBlock, FileEntryExtension, WindowsVersion};
+use crate::linktarget::ItemID;
+use crate::propertystore::{APP_USER_MODEL_FORMAT_ID, APP_USER_MODEL_ID};
+use crate::{
+    controlpanel, filetime, shortname, stringdata, ControlPanelCategory, FileAttributeFlags, Guid,
+    MSLinkError, PropertyStore, PropertyValue,
+};
+use byteorder::{ByteOrder, LE};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// This PC, formerly My Computer.
+const CLSID_MY_COMPUTER: Guid = Guid::from_fields(
+    0x20D04FE0,
+    0x3AEA,
+    0x1069,
+    [0xA2, 0xD8, 0x08, 0x00, 0x2B, 0x30, 0x30, 0x9D],
+);
+/// Network, formerly My Network Places.
+const CLSID_NETWORK: Guid = Guid::from_fields(
+    0x208D2C60,
+    0x3AEA,
+    0x1069,
+    [0xA2, 0xD7, 0x08, 0x00, 0x2B, 0x30, 0x30, 0x9D],
+);
+/// Recycle Bin.
+const CLSID_RECYCLE_BIN: Guid = Guid::from_fields(
+    0x645FF040,
+    0x5081,
+    0x101B,
+    [0x9F, 0x08, 0x00, 0xAA, 0x00, 0x2F, 0x95, 0x4E],
+);
+/// Control Panel, in category view.
+const CLSID_CONTROL_PANEL: Guid = Guid::from_fields(
+    0x26EE0668,
+    0xA00A,
+    0x44D7,
+    [0x93, 0x71, 0xBE, 0xB0, 0x64, 0xC9, 0x86, 0x83],
+);
+/// The user's folder, such as `C:\Users\Admin`.
+const CLSID_USER_FILES: Guid = Guid::from_fields(
+    0x59031A47,
+    0x3F72,
+    0x44A7,
+    [0x89, 0xC5, 0x55, 0x95, 0xFE, 0x6B, 0x30, 0xEE],
+);
+/// Libraries.
+const CLSID_LIBRARIES: Guid = Guid::from_fields(
+    0x031E4825,
+    0x7B94,
+    0x4DC3,
+    [0xB1, 0x31, 0xE9, 0x46, 0xB4, 0x4C, 0x8D, 0xD5],
+);
+/// Quick access, also called Home.
+const CLSID_QUICK_ACCESS: Guid = Guid::from_fields(
+    0x679F85CB,
+    0x0220,
+    0x4080,
+    [0xB2, 0x9B, 0x55, 0x40, 0xCC, 0x05, 0xAA, 0xB6],
+);
+/// Applications, the `shell:AppsFolder` of installed desktop and packaged apps.
+const CLSID_APPS_FOLDER: Guid = Guid::from_fields(
+    0x4234D49B,
+    0x0245,
+    0x4DF3,
+    [0xB7, 0x80, 0x38, 0x93, 0x94, 0x34, 0x56, 0xE1],
+);
+/// The delegate folder of items such as the files in the user's folder.
+const CLSID_DELEGATE_FOLDER: Guid = Guid::from_fields(
+    0x5E591A74,
+    0xDF96,
+    0x48D3,
+    [0x8D, 0x67, 0x17, 0x33, 0xBC, 0xEE, 0x28, 0xBA],
+);
+
+/// The typed contents of an [`ItemID`] in a [`LinkTargetIdList`](crate::LinkTargetIdList).
+///
+/// Items are told apart by their class type indicator, the first byte after ItemIDSize.
+/// Items of other classes, and items that can't be read, are kept as
+/// [`Unknown`](Self::Unknown).
+///
+/// A parsed item converts back to the ItemID it was read from, so lists can be inspected and
+/// rebuilt without losing data this crate doesn't understand.
+#[derive(Clone, Debug)]
+pub enum ShellItem {
+    /// A root folder such as This PC or Network, class type 0x1F.
+    RootFolder(RootFolderItem),
+    /// A drive such as `C:\`, class types 0x20 to 0x2F.
+    Volume(VolumeItem),
+    /// A file or directory, class types 0x30 to 0x3F.
+    FileEntry(FileEntryItem),
+    /// A network server, share or other network location, class types 0x40 to 0x4F.
+    NetworkLocation(NetworkLocationItem),
+    /// A URI, class type 0x61.
+    Uri(UriItem),
+    /// A Control Panel applet, class type 0x71.
+    ControlPanel(ControlPanelItem),
+    /// A Control Panel category, class type 0x01.
+    ControlPanelCategory(ControlPanelCategoryItem),
+    /// A Control Panel applet provided by a CPL file, class type 0x00.
+    ControlPanelCpl(ControlPanelCplItem),
+    /// An app in Applications, class type 0x00.
+    App(AppItem),
+    /// An entry of a compressed (zip) folder, class type 0x52.
+    CompressedFolder(CompressedFolderItem),
+    /// An item of a delegate folder, such as a file in the user's folder, class type 0x74.
+    Delegate(DelegateItem),
+    /// Any other item, kept as read.
+    Unknown(ItemID),
+}
+
+impl ShellItem {
+    /// The class type indicator of the item, 0 for an empty unknown item.
+    pub fn class_type(&self) -> u8 {
+        match self {
+            Self::RootFolder(_) => 0x1f,
+            Self::Volume(item) => item.class_type,
+            Self::FileEntry(item) => item.class_type,
+            Self::NetworkLocation(item) => item.class_type,
+            Self::Uri(_) => 0x61,
+            Self::ControlPanel(_) => 0x71,
+            Self::ControlPanelCategory(_) => 0x01,
+            Self::ControlPanelCpl(_) | Self::App(_) => 0x00,
+            Self::CompressedFolder(_) => 0x52,
+            Self::Delegate(_) => 0x74,
+            Self::Unknown(item) => item.data().first().copied().unwrap_or(0),
+        }
+    }
+}
+
+impl From<&ItemID> for ShellItem {
+    fn from(item: &ItemID) -> Self {
+        let data = item.data();
+        let parsed = match data.first() {
+            Some(0x00) => ControlPanelCplItem::try_from(data)
+                .map(Self::ControlPanelCpl)
+                .or_else(|_| AppItem::try_from(data).map(Self::App)),
+            Some(0x01) => ControlPanelCategoryItem::try_from(data).map(Self::ControlPanelCategory),
+            Some(0x1f) => RootFolderItem::try_from(data).map(Self::RootFolder),
+            Some(0x20..=0x2f) => VolumeItem::try_from(data).map(Self::Volume),
+            Some(0x30..=0x3f) => FileEntryItem::try_from(data).map(Self::FileEntry),
+            Some(class) if class & 0x70 == 0x40 => {
+                NetworkLocationItem::try_from(data).map(Self::NetworkLocation)
+            }
+            Some(0x52) => CompressedFolderItem::try_from(data).map(Self::CompressedFolder),
+            Some(0x61) => UriItem::try_from(data).map(Self::Uri),
+            Some(0x71) => ControlPanelItem::try_from(data).map(Self::ControlPanel),
+            Some(0x74) => DelegateItem::try_from(data).map(Self::Delegate),
+            _ => return Self::Unknown(item.clone()),
+        };
+        parsed.unwrap_or_else(|_| Self::Unknown(item.clone()))
+    }
+}
+
+impl TryFrom<ShellItem> for ItemID {
+    type Error = MSLinkError;
+
+    /// Fails if the item doesn't fit the 16-bit ItemIDSize.
+    fn try_from(item: ShellItem) -> Result<Self, Self::Error> {
+        let data = match item {
+            ShellItem::RootFolder(item) => item.into(),
+            ShellItem::Volume(item) => item.into(),
+            ShellItem::FileEntry(item) => item.into(),
+            ShellItem::NetworkLocation(item) => item.into(),
+            ShellItem::Uri(item) => item.into(),
+            ShellItem::ControlPanel(item) => item.into(),
+            ShellItem::ControlPanelCategory(item) => item.into(),
+            ShellItem::ControlPanelCpl(item) => item.into(),
+            ShellItem::App(item) => item.into(),
+            ShellItem::CompressedFolder(item) => item.into(),
+            ShellItem::Delegate(item) => item.into(),
+            ShellItem::Unknown(item) => return Ok(item),
+        };
+        ItemID::new(data)
+    }
+}
+
+impl fmt::Display for ShellItem {
+    /// Format the item as Explorer names it in a path, e.g. `This PC` or `C:\`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RootFolder(item) => match item.name() {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "{}", item.clsid),
+            },
+            Self::Volume(item) => match (&item.name, item.clsid()) {
+                (Some(name), _) => write!(f, "{}", name),
+                (None, Some(clsid)) => write!(f, "{}", clsid),
+                (None, None) => write!(f, "<volume 0x{:02x}>", item.class_type),
+            },
+            Self::FileEntry(item) => write!(f, "{}", item.long_name()),
+            Self::NetworkLocation(item) => write!(f, "{}", item.location),
+            Self::Uri(item) => write!(f, "{}", item.uri),
+            Self::ControlPanel(item) => match item.canonical_name() {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "{}", item.clsid),
+            },
+            Self::ControlPanelCategory(item) => match item.category() {
+                Some(category) => write!(f, "{}", category),
+                None => write!(f, "<category {}>", item.category_id),
+            },
+            Self::ControlPanelCpl(item) => write!(f, "{}", item.name),
+            Self::App(item) => match item.app_user_model_id() {
+                Some(aumid) => write!(f, "{}", aumid),
+                None => write!(f, "<app>"),
+            },
+            Self::CompressedFolder(item) => write!(f, "{}", item.name),
+            Self::Delegate(item) => write!(f, "{}", item.long_name()),
+            Self::Unknown(_) => write!(f, "<unknown item 0x{:02x}>", self.class_type()),
+        }
+    }
+}
+
+/// A well-known folder at the top of the shell namespace, which links can point at or into
+/// with a [`RootFolderItem`] as their first item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RootFolder {
+    /// This PC, formerly My Computer, holding the local and mapped drives.
+    ThisPc,
+    /// The Recycle Bin.
+    RecycleBin,
+    /// The Control Panel.
+    ControlPanel,
+    /// Network, holding servers and shares.
+    Network,
+    /// The user's folder, such as `C:\Users\Admin`.
+    UserFiles,
+    /// Libraries.
+    Libraries,
+    /// Quick access, also called Home.
+    QuickAccess,
+    /// Applications, `shell:AppsFolder`, holding the desktop and packaged apps of the Start
+    /// menu.
+    Applications,
+}
+
+impl RootFolder {
+    /// All well-known root folders.
+    pub const ALL: [Self; 8] = [
+        Self::ThisPc,
+        Self::RecycleBin,
+        Self::ControlPanel,
+        Self::Network,
+        Self::UserFiles,
+        Self::Libraries,
+        Self::QuickAccess,
+        Self::Applications,
+    ];
+
+    /// The CLSID of the folder.
+    pub fn clsid(self) -> Guid {
+        match self {
+            Self::ThisPc => CLSID_MY_COMPUTER,
+            Self::RecycleBin => CLSID_RECYCLE_BIN,
+            Self::ControlPanel => CLSID_CONTROL_PANEL,
+            Self::Network => CLSID_NETWORK,
+            Self::UserFiles => CLSID_USER_FILES,
+            Self::Libraries => CLSID_LIBRARIES,
+            Self::QuickAccess => CLSID_QUICK_ACCESS,
+            Self::Applications => CLSID_APPS_FOLDER,
+        }
+    }
+
+    /// The folder with the CLSID `clsid`, if it is a well-known one.
+    pub fn from_clsid(clsid: Guid) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|folder| folder.clsid() == clsid)
+    }
+
+    /// The sort index Explorer writes in the folder's item.
+    pub fn sort_index(self) -> u8 {
+        match self {
+            Self::ThisPc => 0x50,
+            Self::RecycleBin => 0x60,
+            Self::ControlPanel => 0x70,
+            Self::Network => 0x58,
+            Self::UserFiles => 0x44,
+            Self::Libraries => 0x42,
+            Self::QuickAccess => 0x00,
+            Self::Applications => 0x80,
+        }
+    }
+
+    /// The English display name of the folder.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ThisPc => "This PC",
+            Self::RecycleBin => "Recycle Bin",
+            Self::ControlPanel => "Control Panel",
+            Self::Network => "Network",
+            Self::UserFiles => "User files",
+            Self::Libraries => "Libraries",
+            Self::QuickAccess => "Quick access",
+            Self::Applications => "Applications",
+        }
+    }
+}
+
+impl fmt::Display for RootFolder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A root folder shell item, naming a folder at the top of the shell namespace by its CLSID.
+#[derive(Clone, Debug)]
+pub struct RootFolderItem {
+    /// Orders the root folders in Explorer, e.g. 0x50 for This PC.
+    sort_index: u8,
+    clsid: Guid,
+    /// Extension blocks following the CLSID, kept as read.
+    extra: Vec<u8>,
+}
+
+impl RootFolderItem {
+    /// Create the item of the root folder `clsid`.
+    pub fn new(sort_index: u8, clsid: Guid) -> Self {
+        Self {
+            sort_index,
+            clsid,
+            extra: vec![],
+        }
+    }
+
+    /// The item for This PC, the root of local and mapped drives.
+    pub fn my_computer() -> Self {
+        RootFolder::ThisPc.into()
+    }
+
+    /// The item for Network, the root of servers and shares.
+    pub fn network() -> Self {
+        RootFolder::Network.into()
+    }
+
+    /// The sort index, which orders the root folders in Explorer.
+    pub fn sort_index(&self) -> u8 {
+        self.sort_index
+    }
+
+    /// The CLSID of the folder.
+    pub fn clsid(&self) -> Guid {
+        self.clsid
+    }
+
+    /// The folder, if it is a well-known one.
+    pub fn folder(&self) -> Option<RootFolder> {
+        RootFolder::from_clsid(self.clsid)
+    }
+
+    /// The display name of well-known folders.
+    pub fn name(&self) -> Option<&'static str> {
+        self.folder().map(RootFolder::name)
+    }
+}
+
+impl From<RootFolder> for RootFolderItem {
+    fn from(folder: RootFolder) -> Self {
+        Self::new(folder.sort_index(), folder.clsid())
+    }
+}
+
+impl TryFrom<&[u8]> for RootFolderItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let clsid = data
+            .get(2..)
+            .and_then(Guid::read)
+            .ok_or_else(|| MSLinkError::truncated("RootFolderItem"))?;
+        Ok(Self {
+            sort_index: data[1],
+            clsid,
+            extra: data[18..].to_vec(),
+        })
+    }
+}
+
+impl From<RootFolderItem> for Vec<u8> {
+    fn from(val: RootFolderItem) -> Self {
+        let mut data = vec![0x1f, val.sort_index];
+        data.extend_from_slice(&val.clsid.to_bytes());
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// A volume shell item, naming a drive such as `C:\`.
+#[derive(Clone, Debug)]
+pub struct VolumeItem {
+    /// 0x20 with flags, 0x01 meaning the item has a name.
+    class_type: u8,
+    name: Option<String>,
+    /// The data following the name, or all data of an item without name, kept as read.
+    extra: Vec<u8>,
+}
+
+impl VolumeItem {
+    /// Create the item of a drive, padded to the size Explorer uses.
+    pub fn new(drive: char) -> Self {
+        Self {
+            class_type: 0x2f,
+            name: Some(format!("{}:\\", drive.to_ascii_uppercase())),
+            extra: vec![0; 18],
+        }
+    }
+
+    /// The drive, such as `C:\`, if the item has a name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The CLSID of an item without name, which names a shell folder such as Documents
+    /// beneath This PC.
+    pub fn clsid(&self) -> Option<Guid> {
+        match self.name {
+            Some(_) => None,
+            None => self.extra.get(1..).and_then(Guid::read),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for VolumeItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let class_type = data[0];
+        if class_type & 0x01 == 0 {
+            return Ok(Self {
+                class_type,
+                name: None,
+                extra: data[1..].to_vec(),
+            });
+        }
+        let (len, name) = stringdata::parse_ansi_z(&data[1..], "VolumeItem")?;
+        Ok(Self {
+            class_type,
+            extra: data[1 + len..].to_vec(),
+            name: Some(name),
+        })
+    }
+}
+
+impl From<VolumeItem> for Vec<u8> {
+    fn from(val: VolumeItem) -> Self {
+        let mut data = vec![val.class_type];
+        if let Some(name) = &val.name {
+            data.append(&mut stringdata::to_ansi_z(name));
+        }
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// A file entry shell item, naming a file or directory below a volume, share or folder.
+#[derive(Clone, Debug)]
+pub struct FileEntryItem {
+    /// 0x30 with flags: 0x01 directory, 0x02 file, 0x04 Unicode name.
+    class_type: u8,
+    /// The size of a file, truncated to 32 bits.
+    file_size: u32,
+    /// The modification time as FAT date and time.
+    modification_time: u32,
+    file_attributes: u16,
+    /// The primary name, the 8.3 short name if the long name doesn't fit 8.3.
+    name: String,
+    /// Whether a padding byte aligns the extension blocks to 16 bits.
+    padding: bool,
+    extension_blocks: Vec<ExtensionBlock>,
+    /// Data following the extension blocks, kept as read.
+    extra: Vec<u8>,
+}
+
+impl FileEntryItem {
+    /// Create the item of a file or directory named `name`, with its generated 8.3 short
+    /// name as the primary name (see [`short_name`](crate::short_name)) and an extension
+    /// block holding the long name, as written by the default [`WindowsVersion`].
+    pub fn new(name: &str, is_dir: bool) -> Self {
+        Self::with_short_name(name, &shortname::short_name(name, |_| false), is_dir)
+    }
+
+    /// Create the item of a file or directory named `name`, whose 8.3 short name is
+    /// `short_name`, such as the one Windows assigned when the entry was created.
+    pub fn with_short_name(name: &str, short_name: &str, is_dir: bool) -> Self {
+        let (file_attributes, class_type) = match (is_dir, short_name.is_ascii()) {
+            (true, true) => (0x0010, 0x31),
+            // 0x04 has unicode string
+            (true, false) => (0x0010, 0x35),
+            (false, true) => (0x0020, 0x32),
+            (false, false) => (0x0020, 0x36),
+        };
+        let mut item = Self {
+            class_type,
+            file_size: 0,
+            modification_time: 0,
+            file_attributes,
+            name: short_name.to_owned(),
+            padding: false,
+            extension_blocks: vec![],
+            extra: vec![],
+        };
+        // the extension blocks are 16-bit aligned
+        let name_end = 12 + item.name_len();
+        item.padding = name_end % 2 == 1;
+        // the offset counts from the start of the item, including its ItemIDSize
+        let first_offset = 2 + name_end + item.padding as usize;
+        let extension =
+            FileEntryExtension::new(WindowsVersion::default(), name, first_offset as u16);
+        item.extension_blocks
+            .push(ExtensionBlock::FileEntry(extension));
+        item
+    }
+
+    /// Change the 0xBEEF0004 extension block, if any, to the layout written by
+    /// `windows_version`, keeping its timestamps and names.
+    pub fn set_windows_version(&mut self, windows_version: WindowsVersion) {
+        for block in &mut self.extension_blocks {
+            if let ExtensionBlock::FileEntry(block) = block {
+                block.set_windows_version(windows_version);
+            }
+        }
+    }
+
+    /// Whether the item is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.class_type & 0x01 != 0
+    }
+
+    /// The size of a file, truncated to 32 bits.
+    pub fn file_size(&self) -> u32 {
+        self.file_size
+    }
+
+    /// Set the size of a file, truncated to 32 bits like the file size in the header.
+    pub fn set_file_size(&mut self, file_size: u64) {
+        self.file_size = file_size as u32;
+    }
+
+    /// The modification time as FAT date (low 16 bits) and time (high 16 bits).
+    pub fn modification_time(&self) -> u32 {
+        self.modification_time
+    }
+
+    /// Set the creation, access and write times from FILETIMEs. The write time is the
+    /// modification time of the item, the others are stored in its 0xBEEF0004 extension
+    /// block, if any. All are stored as FAT date and time with a resolution of two seconds.
+    pub fn set_times(&mut self, creation_time: u64, access_time: u64, write_time: u64) {
+        self.modification_time = filetime::to_fat(write_time);
+        for block in &mut self.extension_blocks {
+            if let ExtensionBlock::FileEntry(block) = block {
+                block.set_times(
+                    filetime::to_fat(creation_time),
+                    filetime::to_fat(access_time),
+                );
+            }
+        }
+    }
+
+    /// The file attributes.
+    pub fn file_attributes(&self) -> FileAttributeFlags {
+        FileAttributeFlags::from_bits_truncate(self.file_attributes as u32)
+    }
+
+    /// The primary name, which is the 8.3 short name in items written by Explorer if the
+    /// long name doesn't fit 8.3.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The long name from the extension block, or the primary name if there is none.
+    pub fn long_name(&self) -> &str {
+        self.extension()
+            .map_or(&self.name, FileEntryExtension::long_name)
+    }
+
+    /// The extension blocks following the primary name.
+    pub fn extension_blocks(&self) -> &[ExtensionBlock] {
+        &self.extension_blocks
+    }
+
+    /// The 0xBEEF0004 extension block with the long name and timestamps, if present.
+    pub fn extension(&self) -> Option<&FileEntryExtension> {
+        self.extension_blocks.iter().find_map(|block| match block {
+            ExtensionBlock::FileEntry(block) => Some(block),
+            ExtensionBlock::Unknown { .. } => None,
+        })
+    }
+
+    fn is_unicode(&self) -> bool {
+        self.class_type & 0x04 != 0
+    }
+
+    /// The size of the primary name including its terminating NULL.
+    fn name_len(&self) -> usize {
+        if self.is_unicode() {
+            self.name.encode_utf16().count() * 2 + 2
+        } else {
+            stringdata::to_ansi_z(&self.name).len()
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for FileEntryItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 12 {
+            return Err(MSLinkError::truncated("FileEntryItem"));
+        }
+        let mut item = Self {
+            class_type: data[0],
+            file_size: LE::read_u32(&data[2..]),
+            modification_time: LE::read_u32(&data[6..]),
+            file_attributes: LE::read_u16(&data[10..]),
+            name: String::new(),
+            padding: false,
+            extension_blocks: vec![],
+            extra: vec![],
+        };
+        let name_len = if item.is_unicode() {
+            item.name = stringdata::parse_unicode_z(&data[12..], "FileEntryItem")?;
+            item.name_len()
+        } else {
+            let (len, name) = stringdata::parse_ansi_z(&data[12..], "FileEntryItem")?;
+            item.name = name;
+            len
+        };
+        let mut rest = &data[12 + name_len..];
+        // data follows ItemIDSize, so odd offsets are odd within the item as well
+        if name_len % 2 == 1 && rest.first() == Some(&0) {
+            item.padding = true;
+            rest = &rest[1..];
+        }
+        let (extension_blocks, rest) = extensionblock::parse_blocks(rest);
+        item.extension_blocks = extension_blocks;
+        item.extra = rest.to_vec();
+        Ok(item)
+    }
+}
+
+impl From<FileEntryItem> for Vec<u8> {
+    fn from(val: FileEntryItem) -> Self {
+        let mut data = vec![0u8; 12];
+        data[0] = val.class_type;
+        LE::write_u32(&mut data[2..], val.file_size);
+        LE::write_u32(&mut data[6..], val.modification_time);
+        LE::write_u16(&mut data[10..], val.file_attributes);
+        if val.is_unicode() {
+            data.append(&mut stringdata::to_unicode_z(&val.name));
+        } else {
+            data.append(&mut stringdata::to_ansi_z(&val.name));
+        }
+        if val.padding {
+            data.push(0);
+        }
+        for block in val.extension_blocks {
+            data.append(&mut block.into());
+        }
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// A network location shell item, naming a server (`\\server`), a share
+/// (`\\server\share`) or another location in the Network folder.
+#[derive(Clone, Debug)]
+pub struct NetworkLocationItem {
+    /// 0x40 with the type of location in the low bits, e.g. 0x42 server and 0x43 share.
+    class_type: u8,
+    unknown: u8,
+    /// 0x80 has description, 0x40 has comments.
+    flags: u8,
+    location: String,
+    description: Option<String>,
+    comments: Option<String>,
+    /// Data following the strings, kept as read.
+    extra: Vec<u8>,
+}
+
+/// Description written by explorer for SMB network location items.
+const NETWORK_DESCRIPTION: &str = "Microsoft Windows Network";
+
+/// Description written by explorer for WebDAV network location items.
+const WEBDAV_DESCRIPTION: &str = "Web Client Network";
+
+/// The kind of a [`NetworkLocationItem`], stored in the low bits of its class type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NetworkLocationType {
+    /// A domain or workgroup.
+    Domain,
+    /// A server, `\\server`.
+    Server,
+    /// A share, `\\server\share`, including WebDAV folders such as
+    /// `\\server@SSL\DavWWWRoot`.
+    Share,
+    /// A network provider, such as Microsoft Windows Network.
+    Provider,
+    /// Entire Network.
+    EntireNetwork,
+    /// A network place, such as a WebDAV or FTP location added to This PC.
+    NetworkPlace,
+    /// Any other kind, with its value.
+    Other(u8),
+}
+
+impl NetworkLocationType {
+    /// The kind of location of the class type `class_type`.
+    pub fn from_class_type(class_type: u8) -> Self {
+        match class_type & 0x0f {
+            0x01 => Self::Domain,
+            0x02 => Self::Server,
+            0x03 => Self::Share,
+            0x06 => Self::Provider,
+            0x07 => Self::EntireNetwork,
+            0x0d => Self::NetworkPlace,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl NetworkLocationItem {
+    /// The item of an SMB location, as written by Explorer with 0x80 set in the class type.
+    fn smb(class_type: u8, location: String) -> Self {
+        Self {
+            class_type: 0x80 | class_type,
+            // unknown but always set by explorer
+            unknown: 0x01,
+            flags: 0x81,
+            location,
+            description: Some(NETWORK_DESCRIPTION.to_owned()),
+            comments: None,
+            extra: vec![],
+        }
+    }
+
+    /// Create the item of an SMB server, `\\server`.
+    pub fn server(server: &str) -> Self {
+        Self::smb(0x42, format!("\\\\{}", server))
+    }
+
+    /// Create the item of an SMB share, `\\server\share`.
+    pub fn share(server: &str, share: &str) -> Self {
+        Self::smb(0x43, format!("\\\\{}\\{}", server, share))
+    }
+
+    /// Create the item of the root of a WebDAV server, `\\server@SSL\DavWWWRoot` over HTTPS
+    /// or `\\server\DavWWWRoot` over HTTP, as the WebClient redirector names it. Folders
+    /// below it are file entry items.
+    pub fn webdav(server: &str, ssl: bool) -> Self {
+        let server = if ssl {
+            format!("{}@SSL", server)
+        } else {
+            server.to_owned()
+        };
+        let mut item = Self::share(&server, "DavWWWRoot");
+        item.description = Some(WEBDAV_DESCRIPTION.to_owned());
+        item
+    }
+
+    /// The kind of location.
+    pub fn location_type(&self) -> NetworkLocationType {
+        NetworkLocationType::from_class_type(self.class_type)
+    }
+
+    /// Whether the location is reached through the WebDAV redirector.
+    pub fn is_webdav(&self) -> bool {
+        self.description.as_deref() == Some(WEBDAV_DESCRIPTION)
+            || self.location.to_ascii_lowercase().contains("davwwwroot")
+    }
+
+    /// The location, such as `\\server\share`.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// The description, such as `Microsoft Windows Network`.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The comments, if any.
+    pub fn comments(&self) -> Option<&str> {
+        self.comments.as_deref()
+    }
+}
+
+impl TryFrom<&[u8]> for NetworkLocationItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 3 {
+            return Err(MSLinkError::truncated("NetworkLocationItem"));
+        }
+        let flags = data[2];
+        let mut cursor = 3;
+        let mut next_string = |present: bool| {
+            if !present {
+                return Ok(None);
+            }
+            let (len, s) = stringdata::parse_ansi_z(&data[cursor..], "NetworkLocationItem")?;
+            cursor += len;
+            Ok::<_, MSLinkError>(Some(s))
+        };
+        let location = next_string(true)?.unwrap_or_default();
+        let description = next_string(flags & 0x80 != 0)?;
+        let comments = next_string(flags & 0x40 != 0)?;
+        Ok(Self {
+            class_type: data[0],
+            unknown: data[1],
+            flags,
+            location,
+            description,
+            comments,
+            extra: data[cursor..].to_vec(),
+        })
+    }
+}
+
+impl From<NetworkLocationItem> for Vec<u8> {
+    fn from(val: NetworkLocationItem) -> Self {
+        let mut data = vec![val.class_type, val.unknown, val.flags];
+        data.append(&mut stringdata::to_ansi_z(&val.location));
+        for s in val.description.iter().chain(&val.comments) {
+            data.append(&mut stringdata::to_ansi_z(s));
+        }
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// A URI shell item, naming a web or other URI.
+///
+/// The item data is the class type, the flags, the 16-bit size of an optional data block
+/// and the URI. The data block holds two unknown 32-bit values, a FILETIME, 20 unknown
+/// bytes and the host, user name and password of FTP URIs, each a string preceded by its
+/// 32-bit size in bytes.
+#[derive(Clone, Debug)]
+pub struct UriItem {
+    /// 0x80 Unicode URI.
+    flags: u8,
+    data: Option<UriData>,
+    uri: String,
+    /// Data following the URI, kept as read.
+    extra: Vec<u8>,
+}
+
+/// The data block of a [`UriItem`].
+#[derive(Clone, Debug)]
+struct UriData {
+    unknown: [u8; 8],
+    timestamp: u64,
+    unknown2: [u8; 20],
+    host: String,
+    user_name: String,
+    password: String,
+    /// Data following the strings, kept as read.
+    extra: Vec<u8>,
+}
+
+impl UriData {
+    fn new(timestamp: u64) -> Self {
+        Self {
+            unknown: [0; 8],
+            timestamp,
+            unknown2: [0; 20],
+            host: String::new(),
+            user_name: String::new(),
+            password: String::new(),
+            extra: vec![],
+        }
+    }
+}
+
+impl UriItem {
+    /// Create the item of a URI, such as `https://example.com/`.
+    pub fn new(uri: &str) -> Self {
+        Self {
+            flags: 0x80,
+            data: None,
+            uri: uri.to_owned(),
+            extra: vec![],
+        }
+    }
+
+    /// The URI.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Whether the URI and the strings of the data block are stored as UTF-16.
+    pub fn is_unicode(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+
+    /// The FILETIME of the data block, such as when the URI was last visited, if the item
+    /// has a data block.
+    pub fn timestamp(&self) -> Option<u64> {
+        self.data.as_ref().map(|data| data.timestamp)
+    }
+
+    /// Set the FILETIME of the data block, adding an empty block if needed.
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        match &mut self.data {
+            Some(data) => data.timestamp = timestamp,
+            None => self.data = Some(UriData::new(timestamp)),
+        }
+    }
+
+    /// The host of an FTP URI, if set.
+    pub fn host(&self) -> Option<&str> {
+        self.data_string(|data| &data.host)
+    }
+
+    /// The user name of an FTP URI, if set.
+    pub fn user_name(&self) -> Option<&str> {
+        self.data_string(|data| &data.user_name)
+    }
+
+    /// The password of an FTP URI, if set.
+    pub fn password(&self) -> Option<&str> {
+        self.data_string(|data| &data.password)
+    }
+
+    fn data_string<F: Fn(&UriData) -> &String>(&self, field: F) -> Option<&str> {
+        self.data
+            .as_ref()
+            .map(field)
+            .filter(|s| !s.is_empty())
+            .map(String::as_str)
+    }
+
+    /// Read a string and its terminator from `data`, returning the string and the bytes read.
+    fn parse_string(&self, data: &[u8]) -> Result<(String, usize), MSLinkError> {
+        if self.is_unicode() {
+            let string = stringdata::parse_unicode_z(data, "UriItem")?;
+            let len = string.encode_utf16().count() * 2 + 2;
+            Ok((string, len))
+        } else {
+            let (len, string) = stringdata::parse_ansi_z(data, "UriItem")?;
+            Ok((string, len))
+        }
+    }
+
+    fn string_to_bytes(&self, string: &str) -> Vec<u8> {
+        if self.is_unicode() {
+            stringdata::to_unicode_z(string)
+        } else {
+            stringdata::to_ansi_z(string)
+        }
+    }
+
+    /// Read the data block, which `data` holds exactly.
+    fn parse_data(&self, data: &[u8]) -> Result<UriData, MSLinkError> {
+        let truncated = || MSLinkError::truncated("UriItem");
+        if data.len() < 36 {
+            return Err(truncated());
+        }
+        let mut offset = 36;
+        let mut strings = vec![];
+        for _ in 0..3 {
+            let size = data
+                .get(offset..offset + 4)
+                .map(LE::read_u32)
+                .ok_or_else(truncated)? as usize;
+            let bytes = data
+                .get(offset + 4..offset + 4 + size)
+                .ok_or_else(truncated)?;
+            strings.push(match size {
+                0 => String::new(),
+                _ => self.parse_string(bytes)?.0,
+            });
+            offset += 4 + size;
+        }
+        let mut unknown = [0; 8];
+        unknown.copy_from_slice(&data[..8]);
+        let mut unknown2 = [0; 20];
+        unknown2.copy_from_slice(&data[16..36]);
+        let password = strings.pop().unwrap();
+        let user_name = strings.pop().unwrap();
+        let host = strings.pop().unwrap();
+        Ok(UriData {
+            unknown,
+            timestamp: LE::read_u64(&data[8..]),
+            unknown2,
+            host,
+            user_name,
+            password,
+            extra: data[offset..].to_vec(),
+        })
+    }
+
+    fn data_to_bytes(&self, data: &UriData) -> Vec<u8> {
+        let mut bytes = data.unknown.to_vec();
+        bytes.extend_from_slice(&data.timestamp.to_le_bytes());
+        bytes.extend_from_slice(&data.unknown2);
+        for string in &[&data.host, &data.user_name, &data.password] {
+            let string = if string.is_empty() {
+                vec![]
+            } else {
+                self.string_to_bytes(string)
+            };
+            bytes.extend_from_slice(&(string.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&string);
+        }
+        bytes.extend_from_slice(&data.extra);
+        bytes
+    }
+}
+
+impl TryFrom<&[u8]> for UriItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = || MSLinkError::truncated("UriItem");
+        if data.len() < 4 {
+            return Err(truncated());
+        }
+        let mut item = Self {
+            flags: data[1],
+            data: None,
+            uri: String::new(),
+            extra: vec![],
+        };
+        let data_size = LE::read_u16(&data[2..]) as usize;
+        let uri_data = data.get(4..4 + data_size).ok_or_else(truncated)?;
+        if data_size > 0 {
+            item.data = Some(item.parse_data(uri_data)?);
+        }
+        let rest = &data[4 + data_size..];
+        let (uri, uri_len) = item.parse_string(rest)?;
+        item.uri = uri;
+        item.extra = rest[uri_len..].to_vec();
+        Ok(item)
+    }
+}
+
+impl From<UriItem> for Vec<u8> {
+    fn from(val: UriItem) -> Self {
+        let mut uri_data = val
+            .data
+            .as_ref()
+            .map(|data| val.data_to_bytes(data))
+            .unwrap_or_default();
+        let mut data = vec![0x61, val.flags, 0, 0];
+        LE::write_u16(&mut data[2..], uri_data.len() as u16);
+        data.append(&mut uri_data);
+        data.append(&mut val.string_to_bytes(&val.uri));
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// A Control Panel item, naming an applet by its CLSID.
+#[derive(Clone, Debug)]
+pub struct ControlPanelItem {
+    /// The unknown fields between the class type and the CLSID, kept as read.
+    header: [u8; 11],
+    clsid: Guid,
+    /// Extension blocks following the CLSID, kept as read.
+    extra: Vec<u8>,
+}
+
+impl ControlPanelItem {
+    /// Create the item of the applet `clsid`.
+    pub fn new(clsid: Guid) -> Self {
+        let mut header = [0; 11];
+        header[0] = 0x80;
+        Self {
+            header,
+            clsid,
+            extra: vec![0; 2],
+        }
+    }
+
+    /// Create the item of the applet with the canonical name `name`, such as
+    /// `Microsoft.WindowsUpdate`, if it is a known one.
+    pub fn from_canonical_name(name: &str) -> Option<Self> {
+        controlpanel::clsid_of(name).map(Self::new)
+    }
+
+    /// The CLSID of the applet.
+    pub fn clsid(&self) -> Guid {
+        self.clsid
+    }
+
+    /// The canonical name of the applet, such as `Microsoft.WindowsUpdate`, if it is a known
+    /// one.
+    pub fn canonical_name(&self) -> Option<&'static str> {
+        controlpanel::canonical_name_of(self.clsid)
+    }
+}
+
+impl TryFrom<&[u8]> for ControlPanelItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let clsid = data
+            .get(12..)
+            .and_then(Guid::read)
+            .ok_or_else(|| MSLinkError::truncated("ControlPanelItem"))?;
+        let mut header = [0; 11];
+        header.copy_from_slice(&data[1..12]);
+        Ok(Self {
+            header,
+            clsid,
+            extra: data[28..].to_vec(),
+        })
+    }
+}
+
+impl From<ControlPanelItem> for Vec<u8> {
+    fn from(val: ControlPanelItem) -> Self {
+        let mut data = vec![0x71];
+        data.extend_from_slice(&val.header);
+        data.extend_from_slice(&val.clsid.to_bytes());
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// The signature of Control Panel category items.
+const CATEGORY_SIGNATURE: u32 = 0x39de2184;
+
+/// A Control Panel category item, naming a category such as Programs by its identifier.
+#[derive(Clone, Debug)]
+pub struct ControlPanelCategoryItem {
+    unknown: u8,
+    category_id: u32,
+    /// Data following the identifier, kept as read.
+    extra: Vec<u8>,
+}
+
+impl ControlPanelCategoryItem {
+    /// Create the item of `category`.
+    pub fn new(category: ControlPanelCategory) -> Self {
+        Self {
+            unknown: 0,
+            category_id: category.id(),
+            extra: vec![],
+        }
+    }
+
+    /// The identifier of the category.
+    pub fn category_id(&self) -> u32 {
+        self.category_id
+    }
+
+    /// The category, if it is a known one.
+    pub fn category(&self) -> Option<ControlPanelCategory> {
+        ControlPanelCategory::from_id(self.category_id)
+    }
+}
+
+impl TryFrom<&[u8]> for ControlPanelCategoryItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 10 || LE::read_u32(&data[2..]) != CATEGORY_SIGNATURE {
+            return Err(MSLinkError::truncated("ControlPanelCategoryItem"));
+        }
+        Ok(Self {
+            unknown: data[1],
+            category_id: LE::read_u32(&data[6..]),
+            extra: data[10..].to_vec(),
+        })
+    }
+}
+
+impl From<ControlPanelCategoryItem> for Vec<u8> {
+    fn from(val: ControlPanelCategoryItem) -> Self {
+        let mut data = vec![0u8; 10];
+        data[0] = 0x01;
+        data[1] = val.unknown;
+        LE::write_u32(&mut data[2..], CATEGORY_SIGNATURE);
+        LE::write_u32(&mut data[6..], val.category_id);
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// The signature of Control Panel CPL file items.
+const CPL_SIGNATURE: u32 = 0xffffff38;
+
+/// A Control Panel item of an applet provided by a CPL file, such as `main.cpl`.
+///
+/// The CPL path, display name and comments follow a fixed header holding the icon index and
+/// the offsets of the display name and comments, in characters from the start of the path.
+#[derive(Clone, Debug)]
+pub struct ControlPanelCplItem {
+    unknown: u8,
+    icon_index: i32,
+    /// The unknown fields between the icon index and the string offsets, kept as read.
+    fields: [u8; 8],
+    name_offset: u16,
+    comments_offset: u16,
+    path: String,
+    name: String,
+    comments: String,
+    /// Data following the strings, kept as read.
+    extra: Vec<u8>,
+}
+
+impl ControlPanelCplItem {
+    /// Create the item of the applet `name` in the CPL file `path`, whose icon is the one at
+    /// `icon_index` in the file.
+    pub fn new(path: &str, name: &str, comments: &str, icon_index: i32) -> Self {
+        let name_offset = path.encode_utf16().count() + 1;
+        let comments_offset = name_offset + name.encode_utf16().count() + 1;
+        Self {
+            unknown: 0,
+            icon_index,
+            fields: [0; 8],
+            // offsets beyond 16 bits also make the item too large
+            name_offset: name_offset as u16,
+            comments_offset: comments_offset as u16,
+            path: path.to_owned(),
+            name: name.to_owned(),
+            comments: comments.to_owned(),
+            extra: vec![],
+        }
+    }
+
+    /// The path of the CPL file, such as `C:\Windows\System32\main.cpl`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The display name of the applet.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The comments describing the applet.
+    pub fn comments(&self) -> &str {
+        &self.comments
+    }
+
+    /// The index of the applet's icon in the CPL file.
+    pub fn icon_index(&self) -> i32 {
+        self.icon_index
+    }
+}
+
+impl TryFrom<&[u8]> for ControlPanelCplItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 22 || LE::read_u32(&data[2..]) != CPL_SIGNATURE {
+            return Err(MSLinkError::truncated("ControlPanelCplItem"));
+        }
+        let mut fields = [0; 8];
+        fields.copy_from_slice(&data[10..18]);
+        // the path, display name and comments
+        let mut offset = 22;
+        let mut strings = vec![];
+        for _ in 0..3 {
+            let string = stringdata::parse_unicode_z(&data[offset..], "ControlPanelCplItem")?;
+            offset += string.encode_utf16().count() * 2 + 2;
+            strings.push(string);
+        }
+        let comments = strings.pop().unwrap();
+        let name = strings.pop().unwrap();
+        let path = strings.pop().unwrap();
+        Ok(Self {
+            unknown: data[1],
+            icon_index: LE::read_i32(&data[6..]),
+            fields,
+            name_offset: LE::read_u16(&data[18..]),
+            comments_offset: LE::read_u16(&data[20..]),
+            path,
+            name,
+            comments,
+            extra: data[offset..].to_vec(),
+        })
+    }
+}
+
+impl From<ControlPanelCplItem> for Vec<u8> {
+    fn from(val: ControlPanelCplItem) -> Self {
+        let mut data = vec![0u8; 22];
+        data[1] = val.unknown;
+        LE::write_u32(&mut data[2..], CPL_SIGNATURE);
+        LE::write_i32(&mut data[6..], val.icon_index);
+        data[10..18].copy_from_slice(&val.fields);
+        LE::write_u16(&mut data[18..], val.name_offset);
+        LE::write_u16(&mut data[20..], val.comments_offset);
+        data.append(&mut stringdata::to_unicode_z(&val.path));
+        data.append(&mut stringdata::to_unicode_z(&val.name));
+        data.append(&mut stringdata::to_unicode_z(&val.comments));
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// The signature of app items, `APPS`.
+const APP_SIGNATURE: &[u8] = b"APPS";
+
+/// An app in Applications, such as a packaged app, holding a property store that names it by
+/// its Application User Model ID (AUMID).
+///
+/// The item data is the class type, an unknown byte, the 16-bit size of the data following
+/// it, the signature `APPS`, the 16-bit size of the property store and the store.
+#[derive(Clone, Debug)]
+pub struct AppItem {
+    unknown: u8,
+    property_store: PropertyStore,
+    /// Data following the property store, kept as read.
+    extra: Vec<u8>,
+}
+
+impl AppItem {
+    /// Create the item of the app with the AUMID `aumid`, such as
+    /// `Microsoft.WindowsCalculator_8wekyb3d8bbwe!App`.
+    pub fn new(aumid: &str) -> Self {
+        let mut property_store = PropertyStore::new();
+        property_store.set(
+            APP_USER_MODEL_FORMAT_ID,
+            APP_USER_MODEL_ID,
+            PropertyValue::String(aumid.to_owned()),
+        );
+        Self {
+            unknown: 0,
+            property_store,
+            extra: vec![],
+        }
+    }
+
+    /// The AUMID of the app, if its property store holds one.
+    pub fn app_user_model_id(&self) -> Option<&str> {
+        self.property_store.app_user_model_id()
+    }
+
+    /// The properties of the app.
+    pub fn property_store(&self) -> &PropertyStore {
+        &self.property_store
+    }
+}
+
+impl TryFrom<&[u8]> for AppItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = || MSLinkError::truncated("AppItem");
+        if data.len() < 10 || &data[4..8] != APP_SIGNATURE {
+            return Err(truncated());
+        }
+        let store_size = LE::read_u16(&data[8..]) as usize;
+        let store = data.get(10..10 + store_size).ok_or_else(truncated)?;
+        Ok(Self {
+            unknown: data[1],
+            property_store: PropertyStore::try_from(store).map_err(|e| e.within("AppItem", 10))?,
+            extra: data[10 + store_size..].to_vec(),
+        })
+    }
+}
+
+impl From<AppItem> for Vec<u8> {
+    fn from(val: AppItem) -> Self {
+        let store: Vec<u8> = (&val.property_store).into();
+        let mut data = vec![0x00, val.unknown, 0, 0];
+        data.extend_from_slice(APP_SIGNATURE);
+        data.extend_from_slice(&(store.len() as u16).to_le_bytes());
+        data.extend_from_slice(&store);
+        let size = (data.len() - 4) as u16;
+        LE::write_u16(&mut data[2..], size);
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// An entry of a compressed (zip) folder, naming a file or directory inside an archive
+/// browsed in Explorer. It follows the file entry item of the archive.
+///
+/// The item data is the class type, an unknown byte, 32 unknown bytes, the modification time
+/// as UTF-16 text in a 48-byte field, the 32-bit character counts of two strings and the
+/// strings as NULL-terminated UTF-16: the path of the entry in the archive and its name.
+#[derive(Clone, Debug)]
+pub struct CompressedFolderItem {
+    unknown: u8,
+    unknown2: [u8; 32],
+    /// `MM/DD/YYYY  HH:MM`, padded with zeros.
+    modification_time: [u8; 48],
+    path: String,
+    name: String,
+    /// Data following the strings, kept as read.
+    extra: Vec<u8>,
+}
+
+impl CompressedFolderItem {
+    /// Create the item of the entry `path` of an archive, such as `docs/readme.txt`, last
+    /// modified at the FILETIME `modification_time`.
+    pub fn new(path: &str, modification_time: u64) -> Self {
+        let name = path.rsplit(&['/', '\\'][..]).next().unwrap_or_default();
+        let mut time = [0; 48];
+        let text = stringdata::to_unicode_z(&filetime::to_short_date_time(modification_time));
+        time[..text.len()].copy_from_slice(&text);
+        Self {
+            unknown: 0,
+            unknown2: [0; 32],
+            modification_time: time,
+            path: path.to_owned(),
+            name: name.to_owned(),
+            extra: vec![],
+        }
+    }
+
+    /// The path of the entry in the archive.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The name of the entry.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The modification time as written by Explorer, such as `09/12/2008  20:27`.
+    pub fn modification_time(&self) -> String {
+        stringdata::parse_unicode_z(&self.modification_time, "CompressedFolderItem").unwrap_or_else(
+            |_| {
+                let chars: Vec<u16> = self.modification_time.chunks(2).map(LE::read_u16).collect();
+                String::from_utf16_lossy(&chars)
+            },
+        )
+    }
+}
+
+impl TryFrom<&[u8]> for CompressedFolderItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = || MSLinkError::truncated("CompressedFolderItem");
+        if data.len() < 90 {
+            return Err(truncated());
+        }
+        let mut cursor = 90;
+        let mut next_string = |chars: usize| {
+            let bytes = data
+                .get(cursor..cursor + chars * 2 + 2)
+                .ok_or_else(truncated)?;
+            let string = stringdata::parse_unicode_z(bytes, "CompressedFolderItem")?;
+            if string.encode_utf16().count() != chars {
+                return Err(truncated());
+            }
+            cursor += bytes.len();
+            Ok(string)
+        };
+        let path = next_string(LE::read_u32(&data[82..]) as usize)?;
+        let name = next_string(LE::read_u32(&data[86..]) as usize)?;
+        let mut unknown2 = [0; 32];
+        unknown2.copy_from_slice(&data[2..34]);
+        let mut modification_time = [0; 48];
+        modification_time.copy_from_slice(&data[34..82]);
+        Ok(Self {
+            unknown: data[1],
+            unknown2,
+            modification_time,
+            path,
+            name,
+            extra: data[cursor..].to_vec(),
+        })
+    }
+}
+
+impl From<CompressedFolderItem> for Vec<u8> {
+    fn from(val: CompressedFolderItem) -> Self {
+        let mut data = vec![0x52, val.unknown];
+        data.extend_from_slice(&val.unknown2);
+        data.extend_from_slice(&val.modification_time);
+        for string in &[&val.path, &val.name] {
+            data.extend_from_slice(&(string.encode_utf16().count() as u32).to_le_bytes());
+        }
+        data.append(&mut stringdata::to_unicode_z(&val.path));
+        data.append(&mut stringdata::to_unicode_z(&val.name));
+        data.extend_from_slice(&val.extra);
+        data
+    }
+}
+
+/// An item of a delegate folder, which wraps a file entry item for a shell folder that
+/// stores its items on disk, such as the user's folder.
+#[derive(Clone, Debug)]
+pub struct DelegateItem {
+    /// The wrapped file entry item.
+    item: FileEntryItem,
+    /// The CLSID of the folder providing the item.
+    class_id: Guid,
+    /// The extension blocks following the CLSIDs, which hold the long name of the item.
+    extension_blocks: Vec<ExtensionBlock>,
+    /// The item as read, as only reading is supported.
+    data: Vec<u8>,
+}
+
+impl DelegateItem {
+    /// The wrapped file entry item.
+    pub fn file_entry(&self) -> &FileEntryItem {
+        &self.item
+    }
+
+    /// The CLSID of the folder providing the item.
+    pub fn class_id(&self) -> Guid {
+        self.class_id
+    }
+
+    /// The extension blocks following the CLSIDs.
+    pub fn extension_blocks(&self) -> &[ExtensionBlock] {
+        &self.extension_blocks
+    }
+
+    /// The long name from the extension blocks, or the name of the wrapped item.
+    pub fn long_name(&self) -> &str {
+        self.extension_blocks
+            .iter()
+            .find_map(|block| match block {
+                ExtensionBlock::FileEntry(block) => Some(block.long_name()),
+                ExtensionBlock::Unknown { .. } => None,
+            })
+            .unwrap_or_else(|| self.item.long_name())
+    }
+}
+
+impl TryFrom<&[u8]> for DelegateItem {
+    type Error = MSLinkError;
+
+    /// Read the item from its data following ItemIDSize.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = || MSLinkError::truncated("DelegateItem");
+        if data.get(4..8) != Some(b"CFSF") || data.len() < 10 {
+            return Err(truncated());
+        }
+        // the wrapped item starts with its own size
+        let item_size = LE::read_u16(&data[8..]) as usize;
+        let item_data = data.get(10..8 + item_size).ok_or_else(truncated)?;
+        let item = FileEntryItem::try_from(item_data)?;
+        // the delegate folder CLSID is followed by the CLSID of the folder providing the item
+        let delegate = CLSID_DELEGATE_FOLDER.to_bytes();
+        let class_start = data[8 + item_size..]
+            .windows(16)
+            .position(|window| window == delegate)
+            .map(|pos| 8 + item_size + pos + 16)
+            .ok_or_else(truncated)?;
+        let class_id = Guid::read(&data[class_start..]).ok_or_else(truncated)?;
+        let (extension_blocks, _) = extensionblock::parse_blocks(&data[class_start + 16..]);
+        Ok(Self {
+            item,
+            class_id,
+            extension_blocks,
+            data: data.to_vec(),
+        })
+    }
+}
+
+impl From<DelegateItem> for Vec<u8> {
+    fn from(val: DelegateItem) -> Self {
+        val.data
+    }
+}
+
+#[cfg(test)]
+fn item(data: &[u8]) -> ItemID {
+    ItemID::new(data.to_vec()).unwrap()
+}
+
+#[test]
+fn test_parse_items() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/example/fixtures/win10_file.lnk"
+    );
+    let sl = crate::ShellLink::open(path).unwrap();
+    let id_list = sl.linktarget().unwrap();
+    assert_eq!(id_list.to_string(), r"This PC > C:\ > test > a.txt");
+
+    let items: Vec<_> = id_list.items().collect();
+    assert!(matches!(&items[0], ShellItem::RootFolder(item) if item.sort_index() == 0x50));
+    match &items[3] {
+        ShellItem::FileEntry(item) => {
+            assert!(!item.is_dir());
+            assert!(item
+                .file_attributes()
+                .contains(FileAttributeFlags::FILE_ATTRIBUTE_ARCHIVE));
+            assert_eq!(item.modification_time(), 0xa369392c);
+            assert_eq!(item.long_name(), "a.txt");
+            assert_eq!(item.extension().map(|block| block.version()), Some(9));
+        }
+        item => panic!("not a file entry: {:?}", item),
+    }
+    // parsed items convert back to the items they were read from
+    for (item, id) in items.into_iter().zip(id_list.item_ids()) {
+        assert_eq!(ItemID::try_from(item).unwrap().data(), id.data());
+    }
+}
+
+#[test]
+fn test_parse_ansi_names() {
+    // names in the system code page can take fewer bytes than their UTF-8 encoding
+    let volume = [0x2f, 0xc9, 0];
+    let parsed = ShellItem::from(&item(&volume));
+    assert!(matches!(&parsed, ShellItem::Volume(item) if item.name() == Some("\u{c9}")));
+    assert_eq!(ItemID::try_from(parsed).unwrap().data(), &volume[..]);
+
+    // an 8.3 name with a character outside ASCII, followed by the long name's block
+    let mut file: Vec<u8> =
+        FileEntryItem::with_short_name("Caf\u{e9}.txt", "CAFE~1.TXT", false).into();
+    assert_eq!(&file[12..16], b"CAFE");
+    file[15] = 0xc9;
+    match ShellItem::from(&item(&file)) {
+        ShellItem::FileEntry(item) => {
+            assert_eq!(item.name(), "CAF\u{c9}~1.TXT");
+            assert_eq!(item.long_name(), "Caf\u{e9}.txt");
+            assert_eq!(
+                ItemID::try_from(ShellItem::FileEntry(item)).unwrap().data(),
+                &file[..]
+            );
+        }
+        item => panic!("not a file entry: {:?}", item),
+    }
+
+    let mut share = vec![0xc3, 0x01, 0x80];
+    share.extend_from_slice(b"\\\\s\xe9\0Caf\xe9\0\x01\x02");
+    match ShellItem::from(&item(&share)) {
+        ShellItem::NetworkLocation(item) => {
+            assert_eq!(item.location(), "\\\\s\u{e9}");
+            assert_eq!(item.description(), Some("Caf\u{e9}"));
+            assert_eq!(
+                ItemID::try_from(ShellItem::NetworkLocation(item))
+                    .unwrap()
+                    .data(),
+                &share[..]
+            );
+        }
+        item => panic!("not a network location item: {:?}", item),
+    }
+}
+
+#[test]
+fn test_parse_other_items() {
+    let mut data = vec![0x61, 0x80, 0, 0];
+    data.append(&mut stringdata::to_unicode_z("https://example.com/"));
+    let uri = ShellItem::from(&item(&data));
+    assert!(matches!(&uri, ShellItem::Uri(item) if item.uri() == "https://example.com/"));
+    // a URI in the system code page with a character outside ASCII
+    let data = [0x61, 0, 0, 0, b'h', 0xe9, 0];
+    let uri = ShellItem::from(&item(&data));
+    assert!(matches!(&uri, ShellItem::Uri(item) if item.uri() == "h\u{e9}"));
+    assert_eq!(ItemID::try_from(uri).unwrap().data(), &data[..]);
+
+    // a data block with a timestamp and the host and user name of an FTP URI
+    let mut block = vec![0u8; 36];
+    LE::write_u64(&mut block[8..], 132_000_000_000_000_000);
+    for string in &["ftp.example.com", "anonymous", ""] {
+        let string = if string.is_empty() {
+            vec![]
+        } else {
+            stringdata::to_unicode_z(string)
+        };
+        block.extend_from_slice(&(string.len() as u32).to_le_bytes());
+        block.extend_from_slice(&string);
+    }
+    let mut data = vec![0x61, 0x80, 0, 0];
+    LE::write_u16(&mut data[2..], block.len() as u16);
+    data.extend_from_slice(&block);
+    data.append(&mut stringdata::to_unicode_z("ftp://ftp.example.com/pub/"));
+    let ftp = ShellItem::from(&item(&data));
+    match &ftp {
+        ShellItem::Uri(item) => {
+            assert_eq!(item.uri(), "ftp://ftp.example.com/pub/");
+            assert_eq!(item.timestamp(), Some(132_000_000_000_000_000));
+            assert_eq!(item.host(), Some("ftp.example.com"));
+            assert_eq!(item.user_name(), Some("anonymous"));
+            assert_eq!(item.password(), None);
+        }
+        item => panic!("not a URI item: {:?}", item),
+    }
+    assert_eq!(ItemID::try_from(ftp).unwrap().data(), &data[..]);
+    let mut stamped = UriItem::new("https://example.com/");
+    stamped.set_timestamp(1);
+    let stamped: Vec<u8> = stamped.into();
+    assert_eq!(LE::read_u16(&stamped[2..]), 48);
+    assert!(matches!(
+        ShellItem::from(&item(&stamped)),
+        ShellItem::Uri(item) if item.timestamp() == Some(1)
+    ));
+    LE::write_u16(&mut data[2..], 20);
+    assert!(matches!(
+        ShellItem::from(&item(&data)),
+        ShellItem::Unknown(_)
+    ));
+
+    let mut data = vec![0x71; 12];
+    data.extend_from_slice(&CLSID_MY_COMPUTER.to_bytes());
+    let control_panel = ShellItem::from(&item(&data));
+    assert!(matches!(control_panel, ShellItem::ControlPanel(_)));
+    assert_eq!(
+        control_panel.to_string(),
+        "{20D04FE0-3AEA-1069-A2D8-08002B30309D}"
+    );
+
+    // control panel categories and CPL applets are told apart from other items by their
+    // signatures
+    let category: Vec<u8> = ControlPanelCategoryItem::new(ControlPanelCategory::Programs).into();
+    assert_eq!(category, [0x01, 0, 0x84, 0x21, 0xde, 0x39, 8, 0, 0, 0]);
+    assert_eq!(ShellItem::from(&item(&category)).to_string(), "Programs");
+    let cpl: Vec<u8> = ControlPanelCplItem::new("main.cpl", "Mouse", "", 0).into();
+    let parsed = ShellItem::from(&item(&cpl));
+    assert!(matches!(&parsed, ShellItem::ControlPanelCpl(item) if item.name() == "Mouse"));
+    assert_eq!(ItemID::try_from(parsed).unwrap().data(), &cpl[..]);
+    for data in &[&[0x01, 0, 1, 2, 3, 4, 0, 0, 0, 0][..], &cpl[..10]] {
+        assert!(matches!(
+            ShellItem::from(&item(data)),
+            ShellItem::Unknown(_)
+        ));
+    }
+
+    // apps are told apart from CPL applets by their signature
+    let app: Vec<u8> = AppItem::new("Microsoft.WindowsCalculator_8wekyb3d8bbwe!App").into();
+    assert_eq!(&app[4..8], b"APPS");
+    assert_eq!(LE::read_u16(&app[2..]) as usize, app.len() - 4);
+    let parsed = ShellItem::from(&item(&app));
+    assert!(matches!(parsed, ShellItem::App(_)));
+    assert_eq!(
+        parsed.to_string(),
+        "Microsoft.WindowsCalculator_8wekyb3d8bbwe!App"
+    );
+    assert_eq!(ItemID::try_from(parsed).unwrap().data(), &app[..]);
+    assert!(matches!(
+        ShellItem::from(&item(&app[..app.len() - 4])),
+        ShellItem::Unknown(_)
+    ));
+
+    // network locations are told apart by the low bits of their class type
+    let server: Vec<u8> = NetworkLocationItem::server("fileserver").into();
+    assert_eq!(&server[..3], [0xc2, 0x01, 0x81]);
+    match ShellItem::from(&item(&server)) {
+        ShellItem::NetworkLocation(item) => {
+            assert_eq!(item.location_type(), NetworkLocationType::Server);
+            assert!(!item.is_webdav());
+        }
+        item => panic!("not a network location item: {:?}", item),
+    }
+    let mut place = vec![0x4d, 0, 0];
+    place.append(&mut stringdata::to_ansi_z("ftp://ftp.example.com/"));
+    assert!(matches!(
+        ShellItem::from(&item(&place)),
+        ShellItem::NetworkLocation(item)
+            if item.location_type() == NetworkLocationType::NetworkPlace
+    ));
+
+    // compressed folder entries whose character counts don't match their strings are kept
+    let mut zip: Vec<u8> = CompressedFolderItem::new("a/b.txt", 0).into();
+    assert_eq!(ShellItem::from(&item(&zip)).to_string(), "b.txt");
+    zip[82] += 1;
+    assert!(matches!(
+        ShellItem::from(&item(&zip)),
+        ShellItem::Unknown(_)
+    ));
+
+    // a file in the user's folder, wrapped by the delegate folder
+    let file: Vec<u8> = FileEntryItem::new("notes.txt", false).into();
+    let mut data = vec![0x74, 0, 0, 0];
+    data.extend_from_slice(b"CFSF");
+    data.extend_from_slice(&(file.len() as u16 + 2).to_le_bytes());
+    data.extend_from_slice(&file);
+    data.extend_from_slice(&[0, 0]);
+    data.extend_from_slice(&CLSID_DELEGATE_FOLDER.to_bytes());
+    data.extend_from_slice(&CLSID_NETWORK.to_bytes());
+    let extension = FileEntryExtension::new(WindowsVersion::Windows8, "Meeting notes.txt", 0x14);
+    data.append(&mut ExtensionBlock::FileEntry(extension).into());
+    let delegate = ShellItem::from(&item(&data));
+    match &delegate {
+        ShellItem::Delegate(item) => assert_eq!(item.class_id(), CLSID_NETWORK),
+        item => panic!("not a delegate item: {:?}", item),
+    }
+    assert_eq!(delegate.to_string(), "Meeting notes.txt");
+
+    // malformed and unknown items are kept
+    for data in &[&[0x1f, 0x50, 0xe0][..], &[0x74, 0, 0, 0, b'X'], &[0x99]] {
+        let unknown = ShellItem::from(&item(data));
+        assert!(matches!(unknown, ShellItem::Unknown(_)));
+        assert_eq!(ItemID::try_from(unknown).unwrap().data(), *data);
+    }
+}
@@ -0,0 +1,133 @@
+/// Characters allowed in 8.3 names besides ASCII letters and digits.
+const SPECIAL_CHARACTERS: &str = "$%'-_@~`!(){}^#&";
+
+/// Whether `name` is a valid 8.3 name, which FAT and NTFS store as it is instead of
+/// generating a short name: a base of one to eight characters and an optional extension of
+/// up to three, made of ASCII letters, digits and `$%'-_@~`!(){}^#&`.
+pub fn is_short_name(name: &str) -> bool {
+    let (base, extension) = match name.find('.') {
+        Some(dot) => (&name[..dot], &name[dot + 1..]),
+        None => (name, ""),
+    };
+    let valid = |part: &str| part.chars().all(is_short_name_char);
+    (1..=8).contains(&base.len())
+        && extension.len() <= 3
+        && !name.ends_with('.')
+        && valid(base)
+        && valid(extension)
+}
+
+/// Generate the 8.3 short name FAT and NTFS give `long_name`, such as `PROGRA~1` for
+/// `Program Files`. Names that are valid 8.3 names already are returned unchanged.
+///
+/// Spaces, leading periods and all periods but the last are dropped, letters are upper-cased
+/// and characters that are illegal in short names, including non-ASCII ones, become `_`.
+/// The base is cut to six characters and followed by the numeric tail `~1`, the extension
+/// cut to three characters. `is_taken` is asked for each candidate, so names already used in
+/// the directory are skipped: `~2` to `~4` are tried next, then, as Windows does, names made
+/// of two characters of the base, four hexadecimal digits hashed from the long name and a
+/// numeric tail. Windows uses an undocumented hash, so these last names may differ from
+/// the ones it picks.
+pub fn short_name<F: Fn(&str) -> bool>(long_name: &str, is_taken: F) -> String {
+    if is_short_name(long_name) {
+        return long_name.to_owned();
+    }
+    let trimmed = long_name.trim_start_matches(&['.', ' '][..]);
+    let (base, extension) = match trimmed.rfind('.') {
+        Some(dot) => (&trimmed[..dot], &trimmed[dot + 1..]),
+        None => (trimmed, ""),
+    };
+    let mut base = map_chars(base);
+    if base.is_empty() {
+        base.push('_');
+    }
+    let extension: String = map_chars(extension).chars().take(3).collect();
+    let candidate = |base: &str, tail: String| {
+        let base: String = base.chars().take(8 - tail.len()).collect();
+        if extension.is_empty() {
+            format!("{}{}", base, tail)
+        } else {
+            format!("{}{}.{}", base, tail, extension)
+        }
+    };
+
+    for n in 1..=4 {
+        let name = candidate(&base, format!("~{}", n));
+        if !is_taken(&name) {
+            return name;
+        }
+    }
+    let prefix: String = base.chars().take(2).collect();
+    let hashed = format!("{}{:04X}", prefix, hash(long_name));
+    let mut n = 1u32;
+    loop {
+        let name = candidate(&hashed, format!("~{}", n));
+        // gives up once the tail fills the whole base
+        if !is_taken(&name) || n == 9_999_999 {
+            return name;
+        }
+        n += 1;
+    }
+}
+
+fn is_short_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || SPECIAL_CHARACTERS.contains(c)
+}
+
+/// Upper-case `part`, drop spaces and periods and replace illegal characters by `_`.
+fn map_chars(part: &str) -> String {
+    part.chars()
+        .filter(|&c| c != ' ' && c != '.')
+        .map(|c| {
+            if is_short_name_char(c) {
+                c.to_ascii_uppercase()
+            } else {
+                // `+,;=[]` and characters outside ASCII
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A 16-bit hash of the UTF-16 code units of `name`.
+fn hash(name: &str) -> u16 {
+    let hash = name.encode_utf16().fold(0x811c_9dc5u32, |hash, unit| {
+        (hash ^ u32::from(unit)).wrapping_mul(0x0100_0193)
+    });
+    (hash >> 16) as u16 ^ hash as u16
+}
+
+#[test]
+fn test_short_name() {
+    let free = |_: &str| false;
+    assert!(is_short_name("a.txt"));
+    assert!(is_short_name("README"));
+    assert!(!is_short_name("archive.tar.gz"));
+    assert!(!is_short_name("my file.txt"));
+    assert!(!is_short_name("data.json"));
+    assert!(!is_short_name("a+b.txt"));
+
+    assert_eq!(short_name("a.txt", free), "a.txt");
+    assert_eq!(short_name("Program Files", free), "PROGRA~1");
+    assert_eq!(short_name("data.json", free), "DATA~1.JSO");
+    assert_eq!(short_name("archive.tar.gz", free), "ARCHIV~1.GZ");
+    assert_eq!(short_name(".gitignore", free), "GITIGN~1");
+    assert_eq!(short_name("a+b;c.txt", free), "A_B_C~1.TXT");
+    assert_eq!(short_name("Résumé 2024.docx", free), "R_SUM_~1.DOC");
+    assert_eq!(short_name("...", free), "_~1");
+    assert_eq!(short_name("[x]=1,2.txt", free), "_X__1_~1.TXT");
+
+    // numeric tails avoid names used in the directory
+    let taken = ["PROGRA~1", "PROGRA~2", "PROGRA~3", "PROGRA~4"];
+    assert_eq!(
+        short_name("Program Files (x86)", |name| taken[..1].contains(&name)),
+        "PROGRA~2"
+    );
+    let hashed = short_name("Program Files (x86)", |name| taken.contains(&name));
+    assert_eq!(hashed.len(), 8);
+    assert!(hashed.starts_with("PR") && hashed.ends_with("~1"));
+    let next = short_name("Program Files (x86)", |name| {
+        taken.contains(&name) || name == hashed
+    });
+    assert_eq!(next, format!("{}~2", &hashed[..6]));
+}
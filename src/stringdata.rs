@@ -1,12 +1,17 @@
-use crate::LinkFlags;
+use crate::{LinkFlags, MSLinkError};
 use byteorder::{ByteOrder, LE};
 use log::debug;
 
-pub fn parse_string(data: &[u8], flags: LinkFlags) -> (usize, String) {
+pub fn parse_string(data: &[u8], flags: LinkFlags) -> Result<(usize, String), MSLinkError> {
+    if data.len() < 2 {
+        return Err(MSLinkError::truncated("StringData"));
+    }
     let result = if !flags.contains(LinkFlags::IS_UNICODE) {
         let char_bytes = LE::read_u16(data) as usize;
         let total_bytes = 2 + char_bytes;
-        let char_data = &data[2..total_bytes];
+        let char_data = data
+            .get(2..total_bytes)
+            .ok_or_else(|| MSLinkError::truncated("StringData"))?;
         // FIXME: Should be decoding with the system default encoding.
         //        This is effectively Latin-1, as that is the first 256 code points
         //        in Unicode.
@@ -19,16 +24,70 @@ pub fn parse_string(data: &[u8], flags: LinkFlags) -> (usize, String) {
     } else {
         let char_count = LE::read_u16(data) as usize;
         let total_bytes = 2 + char_count * 2;
-        let char_data = &data[2..total_bytes];
+        let char_data = data
+            .get(2..total_bytes)
+            .ok_or_else(|| MSLinkError::truncated("StringData"))?;
         let mut u16_chars = vec![0u16; char_count];
         LE::read_u16_into(char_data, &mut u16_chars);
         (total_bytes, String::from_utf16_lossy(&u16_chars))
     };
     debug!("Parsed string: {:?}", &result);
-    result
+    Ok(result)
+}
+
+/// StringData that doesn't survive decoding, such as UTF-16 with unpaired surrogates, as
+/// read. It is written back as long as the string and its encoding are unchanged.
+#[derive(Clone, Debug)]
+pub struct RawString {
+    field: String,
+    value: String,
+    unicode: bool,
+    data: Vec<u8>,
+}
+
+impl RawString {
+    /// Keep `data` for the `value` decoded from it, if encoding `value` doesn't give `data`
+    /// back.
+    pub fn lossy(
+        field: &str,
+        value: &str,
+        flags: LinkFlags,
+        data: &[u8],
+    ) -> Result<Option<Self>, MSLinkError> {
+        if to_data(value, field, flags)? == data {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            field: field.to_owned(),
+            value: value.to_owned(),
+            unicode: flags.contains(LinkFlags::IS_UNICODE),
+            data: data.to_vec(),
+        }))
+    }
 }
 
-pub fn to_data<S: Into<String>>(str_data: S, flags: LinkFlags) -> Vec<u8> {
+/// Encode `value` for `field`, reusing the data it was read from if any of `raw` holds it.
+pub fn to_data_as_read(
+    value: &str,
+    field: &str,
+    flags: LinkFlags,
+    raw: &[RawString],
+) -> Result<Vec<u8>, MSLinkError> {
+    let unicode = flags.contains(LinkFlags::IS_UNICODE);
+    match raw
+        .iter()
+        .find(|raw| raw.field == field && raw.value == value && raw.unicode == unicode)
+    {
+        Some(raw) => Ok(raw.data.clone()),
+        None => to_data(value, field, flags),
+    }
+}
+
+pub fn to_data<S: Into<String>>(
+    str_data: S,
+    field: &str,
+    flags: LinkFlags,
+) -> Result<Vec<u8>, MSLinkError> {
     let s = str_data.into();
     if !flags.contains(LinkFlags::IS_UNICODE) {
         let mut bytes = vec![0u8; 2];
@@ -36,13 +95,95 @@ pub fn to_data<S: Into<String>>(str_data: S, flags: LinkFlags) -> Vec<u8> {
             bytes.push(c as u8); // FIXME: clips non-Latin-1 characters!
         }
         let len = bytes.len() - 2;
+        check_count(len, field)?;
         LE::write_u16(&mut bytes, len as u16); // writes u16 len at the start
-        bytes
+        Ok(bytes)
     } else {
         let utf16: Vec<u16> = s.encode_utf16().collect();
+        check_count(utf16.len(), field)?;
         let mut bytes = vec![0u8; 2 + utf16.len() * 2];
         LE::write_u16(&mut bytes, utf16.len() as u16);
         LE::write_u16_into(&utf16, &mut bytes[2..]);
-        bytes
+        Ok(bytes)
+    }
+}
+
+/// The CountCharacters field of StringData is 16 bits wide.
+fn check_count(count: usize, field: &str) -> Result<(), MSLinkError> {
+    if count > u16::MAX as usize {
+        return Err(MSLinkError::StringTooLong {
+            field: field.to_owned(),
+            len: count,
+        });
     }
+    Ok(())
+}
+
+/// Encode a NULL-terminated string in the system default code page, as used by LinkInfo
+/// and shell items.
+pub fn to_ansi_z(s: &str) -> Vec<u8> {
+    // FIXME: clips non-Latin-1 characters, see `to_data`
+    let mut bytes: Vec<u8> = s.chars().map(|c| c as u8).collect();
+    bytes.push(0);
+    bytes
+}
+
+/// Encode a NULL-terminated UTF-16LE string.
+pub fn to_unicode_z(s: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    for c in s.encode_utf16().chain(std::iter::once(0)) {
+        bytes.extend_from_slice(&c.to_le_bytes());
+    }
+    bytes
+}
+
+/// Decode a NULL-terminated string in the system default code page from the start of `data`,
+/// returning the bytes read including the terminator and the string.
+pub fn parse_ansi_z(data: &[u8], structure: &str) -> Result<(usize, String), MSLinkError> {
+    let len = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| MSLinkError::truncated(structure))?;
+    // FIXME: decodes as Latin-1, see `parse_string`
+    Ok((len + 1, data[..len].iter().map(|&b| b as char).collect()))
+}
+
+/// Decode a NULL-terminated UTF-16LE string from the start of `data`.
+pub fn parse_unicode_z(data: &[u8], structure: &str) -> Result<String, MSLinkError> {
+    let u16_chars: Vec<u16> = data
+        .chunks_exact(2)
+        .map(LE::read_u16)
+        .take_while(|&c| c != 0)
+        .collect();
+    if u16_chars.len() * 2 + 2 > data.len() {
+        return Err(MSLinkError::truncated(structure));
+    }
+    Ok(String::from_utf16_lossy(&u16_chars))
+}
+
+#[test]
+fn test_lone_surrogate_round_trip() {
+    // "a", an unpaired high surrogate, "b"
+    let data = [3, 0, 0x61, 0, 0x00, 0xd8, 0x62, 0];
+    let (len, value) = parse_string(&data, LinkFlags::IS_UNICODE).unwrap();
+    assert_eq!(len, data.len());
+    assert_eq!(value, "a\u{fffd}b");
+    let raw: Vec<RawString> = RawString::lossy("NAME_STRING", &value, LinkFlags::IS_UNICODE, &data)
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(raw.len(), 1);
+    let written = to_data_as_read(&value, "NAME_STRING", LinkFlags::IS_UNICODE, &raw).unwrap();
+    assert_eq!(written, data);
+    // a changed string is encoded again
+    let written = to_data_as_read("ab", "NAME_STRING", LinkFlags::IS_UNICODE, &raw).unwrap();
+    assert_eq!(written, [2, 0, 0x61, 0, 0x62, 0]);
+
+    // strings that decode losslessly aren't kept
+    let data = to_data("ab", "NAME_STRING", LinkFlags::IS_UNICODE).unwrap();
+    assert!(
+        RawString::lossy("NAME_STRING", "ab", LinkFlags::IS_UNICODE, &data)
+            .unwrap()
+            .is_none()
+    );
 }